@@ -0,0 +1,244 @@
+//! IA's. Each gets the actions available to a team and picks one.
+
+pub mod eval;
+pub mod expectimax;
+pub mod mcts;
+pub mod transposition;
+
+use std::time::{Duration, Instant};
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use crate::game_handler::agent::Agent;
+use crate::game_handler::game::{Player, Board, Action, GameView, GameRng};
+use crate::game_handler::game::zobrist;
+use eval::{Evaluator, Material};
+use transposition::TranspositionTable;
+
+type Score = i32;
+
+const AN_AWFUL_LOT: i32 = 99999999;
+const TURNS_AHEAD: i32 = 4;
+/// Deepest `Deepening` goes, however much budget is left.
+const MAX_TURNS_AHEAD: i32 = 12;
+/// `Deepening` remembers 2^16 scores.
+const TABLE_BITS: u32 = 16;
+
+fn max<T: Ord>(a: T, b: T) -> T {
+    if a > b {a} else {b}
+}
+
+fn max_out_of_list<T: Ord + Copy>(array: &Vec<T>) -> T {
+    let mut greatest_value = array[0];
+    for value in array {
+        greatest_value = max(greatest_value, *value);
+    }
+
+    greatest_value
+}
+
+/// Pure function, static. Gets a list of actions, returns most profitable action.
+pub fn ia(actions: Vec<Action>, board: &Board, player: &Player, others: &[&Player]) -> Action {
+    lookahead(actions, board, player, others, TURNS_AHEAD, &Material, &mut Search::unlimited()).unwrap()
+}
+
+/// Same as `ia`, only looking at the next turn.
+pub fn greedy(actions: Vec<Action>, board: &Board, player: &Player, others: &[&Player]) -> Action {
+    lookahead(actions, board, player, others, 0, &Material, &mut Search::unlimited()).unwrap()
+}
+
+/// Plays anything. Baseline for tournaments. Draws from the seed its view
+/// hands out, so games can be replayed from their own seed.
+pub struct Random {
+    rng: GameRng,
+}
+
+impl Random {
+    pub fn new() -> Random {
+        Random {
+            rng: GameRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Agent for Random {
+    fn on_game_start(&mut self, view: &GameView) {
+        self.rng = GameRng::seed_from_u64(view.agent_seed());
+    }
+
+    fn choose_action(&mut self, view: &GameView) -> Action {
+        *view.actions().choose(&mut self.rng).unwrap()
+    }
+}
+
+/// `ia`, never searching past the turn limit.
+pub struct Endgame;
+
+impl Agent for Endgame {
+    fn choose_action(&mut self, view: &GameView) -> Action {
+        debug_assert_eq!(view.turn(), view.team());
+
+        let mut turns = TURNS_AHEAD;
+        if let Some(max) = view.max_rounds() {
+            let seats = view.players().iter().filter(|p| p.is_alive()).count();
+            turns = turns.min((max.saturating_sub(view.rounds()) / seats) as i32);
+        }
+
+        lookahead(view.actions().to_vec(), view.board(), view.me(), &view.others(), turns, &Material, &mut Search::unlimited()).unwrap()
+    }
+}
+
+/// `ia`, searching deeper and deeper until `limit` runs out. Plays the best
+/// action of the deepest search it finished. Scores are kept from one depth
+/// and one turn to the next.
+pub struct Deepening<E: Evaluator = Material> {
+    pub limit: Limit,
+    pub evaluator: E,
+    table: TranspositionTable<Score>,
+    /// Towns seen when `table` was filled. Hashes don't cover the fog.
+    visible: Vec<bool>,
+}
+
+/// How much a search may do per action.
+#[derive(Copy, Clone, Debug)]
+pub enum Limit {
+    /// Nodes, counted over every depth tried.
+    Nodes(usize),
+    Time(Duration),
+}
+
+/// Node count and clock of a search, so it can be called off midway, and
+/// scores it already knows.
+struct Search<'a> {
+    limit: Option<Limit>,
+    start: Instant,
+    nodes: usize,
+    table: Option<&'a mut TranspositionTable<Score>>,
+    /// Hash of the other players, who don't change during a search.
+    others: u64,
+}
+
+impl Deepening {
+    /// Scores positions the way `ia` does.
+    pub fn new(limit: Limit) -> Deepening {
+        Deepening::with_evaluator(limit, Material)
+    }
+}
+
+impl<E: Evaluator> Deepening<E> {
+    pub fn with_evaluator(limit: Limit, evaluator: E) -> Deepening<E> {
+        Deepening {
+            limit,
+            evaluator,
+            table: TranspositionTable::new(TABLE_BITS),
+            visible: vec![],
+        }
+    }
+}
+
+impl<E: Evaluator> Agent for Deepening<E> {
+    fn choose_action(&mut self, view: &GameView) -> Action {
+        let actions = view.actions().to_vec();
+        if self.visible != view.visible() {
+            self.table.clear();
+            self.visible = view.visible().to_vec();
+        }
+
+        // Looking at the next turn is cheap enough to always finish
+        let mut best = lookahead(actions.clone(), view.board(), view.me(), &view.others(), 0, &self.evaluator, &mut Search::unlimited()).unwrap();
+        let mut search = Search::within(self.limit, &mut self.table);
+        for turns in 1..=MAX_TURNS_AHEAD {
+            match lookahead(actions.clone(), view.board(), view.me(), &view.others(), turns, &self.evaluator, &mut search) {
+                Some(action) => best = action,
+                None => break,
+            }
+        }
+
+        best
+    }
+}
+
+impl<'a> Search<'a> {
+    fn unlimited() -> Search<'a> {
+        Search {
+            limit: None,
+            start: Instant::now(),
+            nodes: 0,
+            table: None,
+            others: 0,
+        }
+    }
+
+    fn within(limit: Limit, table: &'a mut TranspositionTable<Score>) -> Search<'a> {
+        Search {
+            limit: Some(limit),
+            table: Some(table),
+            ..Search::unlimited()
+        }
+    }
+
+    /// Counts a node. False once the budget is spent.
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        match self.limit {
+            None => true,
+            Some(Limit::Nodes(n)) => self.nodes <= n,
+            Some(Limit::Time(time)) => self.start.elapsed() < time,
+        }
+    }
+}
+
+/// Best of `actions`, looking `turns` ahead and scoring with `evaluator`.
+/// None if `search` ran out before it was done.
+fn lookahead<E: Evaluator>(actions: Vec<Action>, board: &Board, player: &Player, others: &[&Player], turns: i32, evaluator: &E, search: &mut Search) -> Option<Action> {
+    search.others = others.iter().fold(0, |hash, other| hash ^ other.zobrist());
+
+    let mut best = None;
+    for action in actions {
+        let score = if let Action::Capture(town) = action {
+            let rival = others.iter().find(|r| r.owns(town.id)).unwrap();
+            let chance = player.capture_chance(rival);
+            if chance >= 0.5 { AN_AWFUL_LOT } else { 0 }
+        } else {
+            get_score(&action, board, player, others, turns, evaluator, search)?
+        };
+
+        // Pick the action with the highest score, the last one on ties
+        match best {
+            Some((top, _)) if score < top => {},
+            _ => best = Some((score, action)),
+        }
+    }
+
+    best.map(|(_, action)| action)
+}
+
+fn get_score<E: Evaluator>(action: &Action, board: &Board, player: &Player, others: &[&Player], turns: i32, evaluator: &E, search: &mut Search) -> Option<Score> {
+    if !search.visit() {
+        return None;
+    }
+
+    let mut player2 = *player;
+    player2.do_action(*action);
+    player2.end_turn(board);
+    if turns == 0 {
+        Some((evaluator.evaluate(&player2, others, board) * 1000.0) as Score)
+    } else if let Action::Capture(_) = action {
+        Some(0)
+    } else {
+        let key = zobrist::mix(player2.zobrist() ^ search.others, turns as u64);
+        if let Some(score) = search.table.as_ref().and_then(|table| table.get(key)) {
+            return Some(score);
+        }
+
+        let mut scores = vec![];
+        for action in player2.get_available_actions(board, others) {
+            scores.push(get_score(&action, board, &player2, others, turns-1, evaluator, search)?);
+        }
+        let score = max_out_of_list(&scores);
+        if let Some(table) = &mut search.table {
+            table.insert(key, score);
+        }
+
+        Some(score)
+    }
+}
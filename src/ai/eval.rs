@@ -0,0 +1,84 @@
+//! Evaluation functions: how good a position looks to a search that can't
+//! see the end of the game. Search agents take any of them, so they can be
+//! compared against one another in tournaments.
+
+use crate::game_handler::game::{Player, Board};
+
+/// A capture costs all of the attacker's gold, and towns are what wins, so
+/// they're worth far more than the income they bring in.
+const TOWN_VALUE: f32 = 1000.0;
+/// What influence trades for: `BuyGold` turns 20 of it into 2000 gold.
+const INFLUENCE_IN_GOLD: i32 = 100;
+/// Turns of income counted in a player's worth.
+const INCOME_TURNS: f32 = 3.0;
+
+pub trait Evaluator {
+    /// How well `player` is doing, `others` being everyone else still in
+    /// the game. Higher is better, and a player with nothing should be
+    /// near 0: `Mcts` rewards an alliance's share of everyone's scores.
+    fn evaluate(&self, player: &Player, others: &[&Player], board: &Board) -> f32;
+}
+
+/// Towns held, nothing else. What `Mcts` has always gone by.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Towns;
+
+/// Gold and influence, nothing else. What `ia` has always gone by.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Material;
+
+/// Resources in gold, plus towns and a few turns of income. Specialty
+/// points count through the influence they bring in.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Territory;
+
+/// `Territory`, minus what rivals next door are likely to take on their
+/// next turn. The capital counts for every town, since losing it is losing
+/// the game.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Threat;
+
+impl Evaluator for Towns {
+    fn evaluate(&self, player: &Player, _others: &[&Player], _board: &Board) -> f32 {
+        player.get_towns_id().len() as f32
+    }
+}
+
+impl Evaluator for Material {
+    fn evaluate(&self, player: &Player, _others: &[&Player], _board: &Board) -> f32 {
+        player.gold as f32 / 150.0 + player.influence as f32 * 3.0 / 11.0
+    }
+}
+
+impl Evaluator for Territory {
+    fn evaluate(&self, player: &Player, _others: &[&Player], board: &Board) -> f32 {
+        let resources = |p: &Player| (p.gold + p.influence * INFLUENCE_IN_GOLD) as f32 / 150.0;
+        let mut next = *player;
+        next.end_turn(board);
+
+        player.get_towns_id().len() as f32 * TOWN_VALUE
+            + resources(player)
+            + (resources(&next) - resources(player)) * INCOME_TURNS
+    }
+}
+
+impl Evaluator for Threat {
+    fn evaluate(&self, player: &Player, others: &[&Player], board: &Board) -> f32 {
+        let towns = player.get_towns_id().len() as f32;
+
+        let mut losses = 0.0;
+        for rival in others.iter().filter(|r| r.alliance != player.alliance && r.is_alive()) {
+            let frontier = rival.frontier(board);
+            let stake = if frontier.contains(player.get_capital_id()) {
+                towns * TOWN_VALUE
+            } else if player.get_towns_id().iter().any(|&id| frontier.contains(id)) {
+                TOWN_VALUE
+            } else {
+                continue;
+            };
+            losses += rival.capture_chance(player).clamp(0.0, 1.0) * stake;
+        }
+
+        Territory.evaluate(player, others, board) - losses
+    }
+}
@@ -0,0 +1,257 @@
+//! Expectimax: searches every team's replies, capture dice included.
+
+use crate::game_handler::agent::Agent;
+use crate::game_handler::game::{Game, GameView, Action};
+use crate::game_handler::game::zobrist;
+use super::eval::{Evaluator, Territory};
+use super::transposition::TranspositionTable;
+
+const WIN: f32 = 1e9;
+/// Actions per turn, turn-ending ones excluded.
+const ACTIONS_PER_TURN: usize = 3;
+/// The agent remembers 2^16 positions.
+const TABLE_BITS: u32 = 16;
+
+/// Searches `depth` turns ahead. The searching team and its allies maximize,
+/// everyone else minimizes, and captures average over the dice.
+///
+/// Turns can go on for as long as resources last, so a turn gets a few
+/// actions before it must end. The agent keeps to the same budget when
+/// playing: if it got a fresh one every action, it would always find
+/// something to do before converting, and never convert.
+pub struct Expectimax<E: Evaluator = Territory> {
+    pub depth: usize,
+    /// Scores positions where the search stops.
+    pub evaluator: E,
+    /// Round of the current turn and actions played in it so far.
+    turn: (usize, usize),
+    /// Values of positions already searched, for this agent's alliance.
+    table: TranspositionTable<(f32, Bound)>,
+    /// Towns seen when `table` was filled. Hashes don't cover the fog.
+    visible: Vec<bool>,
+}
+
+/// What a remembered value says: alpha-beta cut some searches short, and
+/// those only bound the true value.
+#[derive(Copy, Clone)]
+enum Bound {
+    Exact,
+    /// At least this much.
+    Lower,
+    /// At most this much.
+    Upper,
+}
+
+/// What stays the same throughout a search.
+struct Context<'a, E> {
+    /// Alliance searching, which maximizes.
+    alliance: usize,
+    evaluator: &'a E,
+    table: &'a mut TranspositionTable<(f32, Bound)>,
+}
+
+/// What's left to search.
+#[derive(Copy, Clone)]
+struct Budget {
+    turns: usize,
+    /// Actions left in the current turn before it must end.
+    actions: usize,
+}
+
+impl Expectimax {
+    pub fn new(depth: usize) -> Expectimax {
+        Expectimax::with_evaluator(depth, Territory)
+    }
+}
+
+impl<E: Evaluator> Expectimax<E> {
+    pub fn with_evaluator(depth: usize, evaluator: E) -> Expectimax<E> {
+        Expectimax {
+            depth,
+            evaluator,
+            turn: (usize::MAX, 0),
+            table: TranspositionTable::new(TABLE_BITS),
+            visible: vec![],
+        }
+    }
+}
+
+impl<E: Evaluator> Agent for Expectimax<E> {
+    fn choose_action(&mut self, view: &GameView) -> Action {
+        let actions = view.actions();
+        let pass = actions[actions.len()-1];
+
+        if self.turn.0 != view.rounds() {
+            self.turn = (view.rounds(), 0);
+        }
+        if self.visible != view.visible() {
+            self.table.clear();
+            self.visible = view.visible().to_vec();
+        }
+        if self.depth == 0 {
+            return pass;
+        }
+
+        let mut game = view.to_game();
+        let mut context = Context {
+            alliance: view.me().alliance,
+            evaluator: &self.evaluator,
+            table: &mut self.table,
+        };
+        let budget = Budget {
+            turns: self.depth,
+            actions: ACTIONS_PER_TURN.saturating_sub(self.turn.1),
+        };
+
+        // Passing is last, it's searched first so it wins ties
+        let mut best = (play(&mut game, pass, budget, f32::NEG_INFINITY, f32::INFINITY, &mut context), pass);
+        for &action in actions[..actions.len()-1].iter().filter(|&&a| searched(a, budget)) {
+            let value = play(&mut game, action, budget, best.0, f32::INFINITY, &mut context);
+            if value > best.0 {
+                best = (value, action);
+            }
+        }
+
+        self.turn.1 += 1;
+
+        best.1
+    }
+}
+
+fn ends_turn(action: Action) -> bool {
+    matches!(action, Action::Pass | Action::Capture(_) | Action::Convert(_))
+}
+
+fn searched(action: Action, budget: Budget) -> bool {
+    budget.actions > 0 || ends_turn(action)
+}
+
+/// Value of playing `action`. Captures are chance nodes: the dice either
+/// take the town or don't. `game` is played on and put back as it was.
+fn play<E: Evaluator>(game: &mut Game, action: Action, budget: Budget, alpha: f32, beta: f32, context: &mut Context<E>) -> f32 {
+    let team = game.team;
+    let next_turn = Budget {
+        turns: budget.turns - 1,
+        actions: ACTIONS_PER_TURN,
+    };
+
+    match action {
+        Action::Capture(town) => {
+            let rival = match game.get_owner(town.id) {
+                Some(owner) => game.get_player(owner),
+                None => return f32::NEG_INFINITY,
+            };
+            let chance = game.get_player(team).capture_chance(rival).clamp(0.0, 1.0);
+
+            // A roll of 0 always succeeds, 1 never does. Alpha-beta bounds
+            // don't hold across outcomes, so both get a full window.
+            let mut value = 0.0;
+            for (roll, odds) in [(0.0, chance), (1.0, 1.0 - chance)] {
+                if odds > 0.0 {
+                    let undo = match game.apply(action, roll) {
+                        Ok(undo) => undo,
+                        Err(_) => return f32::NEG_INFINITY,
+                    };
+                    value += odds * search(game, next_turn, f32::NEG_INFINITY, f32::INFINITY, context);
+                    game.undo(undo);
+                }
+            }
+
+            value
+        },
+        _ => {
+            let round = game.rounds;
+            let undo = match game.apply(action, 0.0) {
+                Ok(undo) => undo,
+                Err(_) => return f32::NEG_INFINITY,
+            };
+
+            let budget = if game.rounds != round {
+                next_turn
+            } else {
+                Budget {
+                    turns: budget.turns,
+                    actions: budget.actions - 1,
+                }
+            };
+            let value = search(game, budget, alpha, beta, context);
+            game.undo(undo);
+
+            value
+        },
+    }
+}
+
+fn search<E: Evaluator>(game: &mut Game, budget: Budget, mut alpha: f32, mut beta: f32, context: &mut Context<E>) -> f32 {
+    if game.over || budget.turns == 0 {
+        return evaluate(game, context.alliance, context.evaluator);
+    }
+
+    // Positions play out the same however many rounds and actions are
+    // left, as long as the search can't reach the limits
+    let rounds_left = game.config.max_rounds.map_or(0, |max| max.saturating_sub(game.rounds).min(budget.turns + 1));
+    let actions_left = game.config.max_actions.map_or(0, |max| max.saturating_sub(game.turn_actions).min(budget.actions + 1));
+    let depth = budget.turns * (ACTIONS_PER_TURN + 1) + budget.actions;
+    let key = zobrist::mix(zobrist::mix(game.zobrist(), depth as u64), (rounds_left * (ACTIONS_PER_TURN + 2) + actions_left) as u64);
+    if let Some((value, bound)) = context.table.get(key) {
+        match bound {
+            Bound::Exact => return value,
+            Bound::Lower => alpha = alpha.max(value),
+            Bound::Upper => beta = beta.min(value),
+        }
+        if alpha >= beta {
+            return value;
+        }
+    }
+    let window = (alpha, beta);
+
+    let maximizing = game.get_player(game.team).alliance == context.alliance;
+    let mut best = if maximizing { f32::NEG_INFINITY } else { f32::INFINITY };
+    for action in game.get_available_actions(game.team).into_iter().filter(|&a| searched(a, budget)) {
+        let value = play(game, action, budget, alpha, beta, context);
+        if maximizing {
+            best = best.max(value);
+            alpha = alpha.max(best);
+        } else {
+            best = best.min(value);
+            beta = beta.min(best);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+
+    let bound = if best <= window.0 {
+        Bound::Upper
+    } else if best >= window.1 {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    context.table.insert(key, (best, bound));
+
+    best
+}
+
+/// How good `game` looks for `alliance`: its players' scores minus its
+/// enemies'.
+fn evaluate<E: Evaluator>(game: &Game, alliance: usize, evaluator: &E) -> f32 {
+    if game.over {
+        let winners = game.get_winners();
+        return match winners.first() {
+            None => 0.0,
+            Some(&team) if game.get_player(team).alliance == alliance => WIN,
+            Some(_) => -WIN,
+        };
+    }
+
+    let alive: Vec<_> = game.get_players().iter().filter(|p| p.is_alive()).collect();
+    let mut value = 0.0;
+    for player in &alive {
+        let others: Vec<_> = alive.iter().copied().filter(|p| p.team != player.team).collect();
+        let score = evaluator.evaluate(player, &others, game.get_board());
+        value += if player.alliance == alliance { score } else { -score };
+    }
+
+    value
+}
@@ -0,0 +1,211 @@
+//! Monte Carlo tree search: plays random games through the real engine and
+//! grows a tree towards the actions that win them, UCT picking the way.
+
+use std::time::{Duration, Instant};
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use crate::game_handler::agent::Agent;
+use crate::game_handler::game::{Game, GameRng, GameView, Action, Team};
+use super::eval::{Evaluator, Towns};
+
+/// UCT exploration constant, the textbook √2.
+const EXPLORATION: f32 = 1.414;
+/// Rollouts stop after this many turns, scored by the evaluator if still
+/// going.
+const ROLLOUT_TURNS: usize = 20;
+
+/// How long to think about each action.
+#[derive(Copy, Clone, Debug)]
+pub enum Budget {
+    /// Playouts per action.
+    Iterations(usize),
+    /// Time per action.
+    Time(Duration),
+}
+
+/// UCT with random rollouts. The tree is over actions only: dice are rolled
+/// afresh on every playout, so captures average out over the visits.
+pub struct Mcts<E: Evaluator = Towns> {
+    pub budget: Budget,
+    /// Scores rollouts cut short.
+    pub evaluator: E,
+    /// Reseeded from the view on game start, so a game with only iteration
+    /// budgets plays the same again from its own seed.
+    rng: GameRng,
+}
+
+struct Node {
+    /// Action leading here, and the team that played it.
+    action: Option<(Team, Action)>,
+    children: Vec<usize>,
+    /// Actions with no child yet, filled in on the first visit.
+    untried: Option<Vec<Action>>,
+    visits: u32,
+    /// Summed rewards of the alliance that played `action`.
+    reward: f32,
+}
+
+impl Mcts {
+    /// Scores rollouts cut short on the share of towns held.
+    pub fn new(budget: Budget) -> Mcts {
+        Mcts::with_evaluator(budget, Towns)
+    }
+}
+
+impl<E: Evaluator> Mcts<E> {
+    pub fn with_evaluator(budget: Budget, evaluator: E) -> Mcts<E> {
+        Mcts {
+            budget,
+            evaluator,
+            rng: GameRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl<E: Evaluator> Agent for Mcts<E> {
+    fn on_game_start(&mut self, view: &GameView) {
+        self.rng = GameRng::seed_from_u64(view.agent_seed());
+    }
+
+    fn choose_action(&mut self, view: &GameView) -> Action {
+        let actions = view.actions();
+        if actions.len() == 1 {
+            return actions[0];
+        }
+
+        let root = view.to_game();
+        let mut tree = vec![Node::new(None)];
+        tree[0].untried = Some(actions.to_vec());
+
+        let start = Instant::now();
+        let mut iterations = 0;
+        loop {
+            match self.budget {
+                Budget::Iterations(n) if iterations >= n => break,
+                Budget::Time(limit) if start.elapsed() >= limit => break,
+                _ => {},
+            }
+            playout(&mut tree, root.clone(), &self.evaluator, &mut self.rng);
+            iterations += 1;
+        }
+
+        // The most visited action is the most trusted one
+        let best = tree[0].children.iter().max_by_key(|&&child| tree[child].visits);
+        match best.and_then(|&child| tree[child].action) {
+            Some((_, action)) => action,
+            None => actions[actions.len()-1],
+        }
+    }
+}
+
+impl Node {
+    fn new(action: Option<(Team, Action)>) -> Node {
+        Node {
+            action,
+            children: vec![],
+            untried: None,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+
+    fn uct(&self, parent_visits: u32) -> f32 {
+        self.reward / self.visits as f32
+            + EXPLORATION * ((parent_visits as f32).ln() / self.visits as f32).sqrt()
+    }
+}
+
+/// One iteration: walks down the tree, adds a node, plays the rest of the
+/// game at random and credits the result to every node on the way.
+fn playout<E: Evaluator, R: Rng>(tree: &mut Vec<Node>, mut game: Game, evaluator: &E, rng: &mut R) {
+    let mut path = vec![0];
+    let mut node = 0;
+
+    // Selection and expansion
+    while !game.over {
+        let team = game.team;
+        let untried = tree[node].untried.get_or_insert_with(|| game.get_available_actions(team));
+        let next = if !untried.is_empty() {
+            let action = untried.swap_remove(rng.gen_range(0..untried.len()));
+            tree.push(Node::new(Some((team, action))));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            Some(child)
+        } else {
+            let visits = tree[node].visits;
+            tree[node].children.iter().copied()
+                .max_by(|&a, &b| tree[a].uct(visits).total_cmp(&tree[b].uct(visits)))
+        };
+
+        let child = match next {
+            Some(child) => child,
+            None => break,
+        };
+        let (team, action) = tree[child].action.unwrap();
+        step(&mut game, team, action, rng);
+        path.push(child);
+        node = child;
+
+        if tree[child].visits == 0 {
+            break;
+        }
+    }
+
+    rollout(&mut game, rng);
+
+    // Backpropagation
+    for &node in &path {
+        tree[node].visits += 1;
+        if let Some((team, _)) = tree[node].action {
+            tree[node].reward += reward(&game, team, evaluator);
+        }
+    }
+}
+
+/// Plays `action`, rolling the dice for captures. The tree doesn't follow
+/// the dice, so an action picked after a capture may not apply to how it
+/// went this time: whoever's turn it is passes instead.
+fn step<R: Rng>(game: &mut Game, team: Team, action: Action, rng: &mut R) {
+    if game.do_action_rolled(action, team, rng.gen()).is_err() {
+        let team = game.team;
+        game.do_action_rolled(Action::Pass, team, 0.0).unwrap();
+    }
+}
+
+/// Random actions until the game is over or `ROLLOUT_TURNS` have gone by.
+fn rollout<R: Rng>(game: &mut Game, rng: &mut R) {
+    let end = game.rounds + ROLLOUT_TURNS;
+    while !game.over && game.rounds < end {
+        let team = game.team;
+        let action = *game.get_available_actions(team).choose(rng).unwrap();
+        step(game, team, action, rng);
+    }
+}
+
+/// How the game went for `team`'s alliance, from 0 to 1. Unfinished games
+/// count the alliance's share of everyone's scores, negative ones counting
+/// as nothing.
+fn reward<E: Evaluator>(game: &Game, team: Team, evaluator: &E) -> f32 {
+    let alliance = game.get_player(team).alliance;
+    if game.over {
+        let winners = game.get_winners();
+        return match winners.first() {
+            None => 0.5,
+            Some(&winner) if game.get_player(winner).alliance == alliance => 1.0,
+            Some(_) => 0.0,
+        };
+    }
+
+    let alive: Vec<_> = game.get_players().iter().filter(|p| p.is_alive()).collect();
+    let mut scores = (0.0, 0.0);
+    for player in &alive {
+        let others: Vec<_> = alive.iter().copied().filter(|p| p.team != player.team).collect();
+        let score = evaluator.evaluate(player, &others, game.get_board()).max(0.0);
+        if player.alliance == alliance {
+            scores.0 += score;
+        }
+        scores.1 += score;
+    }
+
+    if scores.1 > 0.0 { scores.0 / scores.1 } else { 0.5 }
+}
@@ -0,0 +1,40 @@
+//! Transposition tables: scores of positions already searched, keyed by
+//! their Zobrist hash, so a search doesn't redo a position it reached
+//! through another order of actions, or on an earlier turn.
+
+/// Fixed-size table of `T`s. Each hash has a single slot and newer entries
+/// replace older ones, so memory stays put however long the game goes.
+pub struct TranspositionTable<T> {
+    entries: Vec<Option<(u64, T)>>,
+}
+
+impl<T: Copy> TranspositionTable<T> {
+    /// Table of 2^`bits` entries.
+    pub fn new(bits: u32) -> TranspositionTable<T> {
+        TranspositionTable {
+            entries: vec![None; 1 << bits],
+        }
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        hash as usize & (self.entries.len() - 1)
+    }
+
+    pub fn get(&self, hash: u64) -> Option<T> {
+        match self.entries[self.slot(hash)] {
+            Some((key, value)) if key == hash => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, hash: u64, value: T) {
+        let slot = self.slot(hash);
+        self.entries[slot] = Some((hash, value));
+    }
+
+    /// Forgets every entry, e.g. when the fog lifts and the positions they
+    /// were searched from turn out different.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
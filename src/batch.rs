@@ -0,0 +1,118 @@
+//! Batches of independent seeded games, farmed out to worker threads.
+
+use std::fmt;
+use std::thread;
+use crate::game_handler::GameHandler;
+use crate::game_handler::agent::AgentFactory;
+use crate::game_handler::game::{Game, GameConfig, Team, Ending};
+use crate::game_handler::result::GameResult;
+use crate::tournament::{MAX_ROUNDS, MAX_ACTIONS};
+
+/// Aggregated outcomes of a batch.
+#[derive(Clone, Debug)]
+pub struct BatchStats {
+    pub games: usize,
+    /// Teams in turn order.
+    pub teams: Vec<Team>,
+    /// Games won by each team, in turn order. Allies share their wins.
+    pub wins: Vec<usize>,
+    pub draws: usize,
+    pub conquests: usize,
+    pub victories: usize,
+    pub turn_limits: usize,
+    pub total_rounds: usize,
+    pub shortest: usize,
+    pub longest: usize,
+}
+
+/// Plays `games` games of `agent` against itself over `threads` threads,
+/// seeds counting up from the config seed. Nothing is drawn nor printed.
+pub fn run(agent: AgentFactory, config: &GameConfig, games: usize, threads: usize) -> BatchStats {
+    let threads = threads.max(1);
+    let results: Vec<GameResult> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| scope.spawn(move || {
+                (worker..games).step_by(threads).map(|k| play(agent, config, k)).collect::<Vec<_>>()
+            }))
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+
+    BatchStats::new(&config.teams, &results)
+}
+
+fn play(agent: AgentFactory, config: &GameConfig, k: usize) -> GameResult {
+    let mut config = config.clone();
+    config.seed = config.seed.wrapping_add(k as u64);
+    config.max_rounds = config.max_rounds.or(Some(MAX_ROUNDS));
+    config.max_actions = config.max_actions.or(Some(MAX_ACTIONS));
+
+    let agents = config.teams.iter().map(|_| agent()).collect();
+    let mut gh = GameHandler::with_agents(agents, Game::init(config));
+    gh.set_verbose(false);
+
+    gh.simulate()
+}
+
+impl BatchStats {
+    fn new(teams: &[Team], results: &[GameResult]) -> BatchStats {
+        let mut stats = BatchStats {
+            games: results.len(),
+            teams: teams.to_vec(),
+            wins: vec![0; teams.len()],
+            draws: 0,
+            conquests: 0,
+            victories: 0,
+            turn_limits: 0,
+            total_rounds: 0,
+            shortest: results.iter().map(|r| r.rounds).min().unwrap_or(0),
+            longest: results.iter().map(|r| r.rounds).max().unwrap_or(0),
+        };
+
+        for result in results {
+            for (seat, team) in teams.iter().enumerate() {
+                if result.winners.contains(team) {
+                    stats.wins[seat] += 1;
+                }
+            }
+            if result.winners.is_empty() {
+                stats.draws += 1;
+            }
+            match result.ending {
+                Ending::Conquest => stats.conquests += 1,
+                Ending::Victory(_) => stats.victories += 1,
+                Ending::TurnLimit => stats.turn_limits += 1,
+            }
+            stats.total_rounds += result.rounds;
+        }
+
+        stats
+    }
+
+    /// Share of the games won by the team moving first.
+    pub fn first_mover_advantage(&self) -> f64 {
+        self.win_rate(0)
+    }
+
+    pub fn win_rate(&self, seat: usize) -> f64 {
+        self.wins[seat] as f64 / self.games.max(1) as f64
+    }
+
+    pub fn average_length(&self) -> f64 {
+        self.total_rounds as f64 / self.games.max(1) as f64
+    }
+}
+
+impl fmt::Display for BatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} games", self.games)?;
+        for (seat, team) in self.teams.iter().enumerate() {
+            writeln!(f, "{:>8} wins {:>6} ({:.1}%)", team.to_string(), self.wins[seat], 100.0 * self.win_rate(seat))?;
+        }
+        writeln!(f, "{:>13} {:>6}", "draws", self.draws)?;
+        writeln!(f, "First mover wins {:.1}% of the games", 100.0 * self.first_mover_advantage())?;
+        writeln!(f, "Length: {:.1} turns on average, {} to {}", self.average_length(), self.shortest, self.longest)?;
+        writeln!(f, "Endings: {} conquests, {} victories, {} turn limits", self.conquests, self.victories, self.turn_limits)
+    }
+}
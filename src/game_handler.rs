@@ -2,85 +2,284 @@
 
 mod renderer;
 pub mod game;
+pub mod replay;
+pub mod result;
+pub mod observer;
+pub mod agent;
 extern crate image;
 
-use renderer::Renderer;
-use game::{Game, Action, Town, Player, Team};
-use image::{RgbaImage, open};
-use image::imageops::overlay;
+use game::{Game, Action, Board, Player, Team};
+use replay::Replay;
+use result::{GameResult, Series};
+use observer::{Observer, Frames};
+use agent::Agent;
 
-type Ia<'a> = &'a dyn Fn(Vec<Action>, &Vec<Town>, &Player, &Player) -> Action;
+type Ia<'a> = &'a dyn Fn(Vec<Action>, &Board, &Player, &[&Player]) -> Action;
+
+/// What to do when an IA plays an illegal action.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum IllegalActionPolicy {
+    /// The team is out, its towns go free.
+    #[default]
+    Forfeit,
+    /// The action is replaced by a pass.
+    Pass,
+    /// Crash, handy when debugging an IA.
+    Panic,
+}
 
 pub struct GameHandler {
-    town_spr: image::DynamicImage,
-    renderer: Renderer,
     game: Game,
-    ia: Ia<'static>,
+    /// Agent playing each seat, in turn order.
+    agents: Vec<Box<dyn Agent>>,
+    observers: Vec<Box<dyn Observer>>,
+    verbose: bool,
+    checkpoint: Option<String>,
+    replay: Replay,
+    record: Option<String>,
+    policy: IllegalActionPolicy,
+    series: Vec<Series>,
 }
 
 impl GameHandler {
-    pub fn new(ia: Ia<'static>) -> GameHandler {
-        println!("Initializing renderer [1/2]");
-        let renderer = Renderer::init();
-        println!("Initializing game instance [2/2]");
-        let game = Game::init();
+    /// Moderates an already set up game, e.g. one loaded from a map file.
+    pub fn with_game(ia: Ia<'static>, game: Game) -> GameHandler {
+        let frames = Frames::new(&game);
+        let mut gh = GameHandler::headless(ia, game);
+        gh.add_observer(Box::new(frames));
+
+        gh
+    }
 
-        let town_spr = match open("src/assets/town.png") {
-            Ok(spr) => spr,
-            Err(_) => panic!()
-        };
+    /// Moderates a game without drawing it, for batch experiments.
+    pub fn headless(ia: Ia<'static>, game: Game) -> GameHandler {
+        let agents = game.get_players().iter().map(|_| Box::new(ia) as Box<dyn Agent>).collect();
+        GameHandler::with_agents(agents, game)
+    }
+
+    /// Moderates a game between `agents`, one per seat in turn order,
+    /// without drawing it.
+    pub fn with_agents(agents: Vec<Box<dyn Agent>>, game: Game) -> GameHandler {
+        assert!(agents.len() == game.get_players().len(), "every team needs an agent");
 
         GameHandler {
-            town_spr: town_spr,
-            renderer: renderer,
+            replay: Replay::new(&game),
+            series: Series::for_game(&game),
+            agents,
             game: game,
-            ia: ia,
+            observers: vec![],
+            verbose: true,
+            checkpoint: None,
+            record: None,
+            policy: IllegalActionPolicy::default(),
         }
     }
 
-    fn render(&mut self, round: usize) -> () {
-        self.renderer.render(
-            self.game.get_towns_id(Team::Blue),
-            self.game.get_capital_id(Team::Blue),
-            self.game.get_towns_id(Team::Red),
-            self.game.get_capital_id(Team::Red),
-        );
+    /// Notifies `observer` of every round from now on.
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    fn seat(&self, team: Team) -> usize {
+        self.game.get_players().iter().position(|p| p.team == team).unwrap()
+    }
+
+    /// Turns the printouts of the handler and the game on or off.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+        self.game.quiet = !verbose;
+    }
+
+    /// Saves the game state to `path` after every round.
+    pub fn set_checkpoint(&mut self, path: &str) {
+        self.checkpoint = Some(path.to_string());
+    }
+
+    /// Writes a replay of the game to `path` once it's over.
+    pub fn set_record(&mut self, path: &str) {
+        self.record = Some(path.to_string());
+    }
 
-        if let Some(mut image) = RgbaImage::from_raw(1000, 1000, self.renderer.im_buff.clone()) {
-            for i in 0..(4*4) {
-                let t = self.renderer.towns.get_town(i as usize);
-                let pt = self.renderer.cache.get_pixel(t.x, t.y);
-                overlay(&mut image, &self.town_spr, (pt.x-25) as i64, (pt.y-25) as i64);
+    pub fn set_policy(&mut self, policy: IllegalActionPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Stuff that happens once a team ends its turn
+    fn end_round(&mut self, round: usize, team: Team) {
+        if self.verbose {
+            println!("Round {}, team {}", round, team); // Debug
+            self.game.print_stats(team);                // purposes
+        }
+
+        Series::record(&mut self.series, &self.game);
+
+        // Render frame and such
+        for observer in &mut self.observers {
+            observer.on_round(round, team, &self.game);
+        }
+
+        if let Some(path) = &self.checkpoint {
+            if let Err(e) = self.game.save(path) {
+                println!("Checkpoint failed: {}", e);
             }
-            let _res = image.save(format!("src/assets/{round}.png"));
         }
     }
 
-    pub fn simulate(&mut self) -> () {
-        self.game.print_map(); // Debugging purposes
-        loop {
+    /// Stuff that happens once the game is over, played or replayed
+    fn end_game(&mut self) {
+        if self.verbose {
+            for winner in self.game.get_winners() {
+                println!("{} wins", winner);
+            }
+            if self.game.is_draw() {
+                println!("Draw");
+            }
+        }
+        for observer in &mut self.observers {
+            observer.on_game_end(&self.game);
+        }
+    }
+
+    pub fn simulate(&mut self) -> GameResult {
+        if self.verbose {
+            println!("Seed: {}", self.game.config.seed); // Replay the game with this seed
+            self.game.print_map(); // Debugging purposes
+        }
+        for (agent, player) in self.agents.iter_mut().zip(self.game.get_players()) {
+            agent.on_game_start(&self.game.view(player.team));
+        }
+        // A loaded game may be over already
+        while !self.game.over {
             let team = self.game.team;
             let round = self.game.rounds;
 
-            let actions = self.game.get_available_actions(team);
-            let action = (self.ia)(
-                actions,
-                self.game.get_towns(),
-                self.game.get_player(team),
-                self.game.get_player(team.rival()),
-            );
-            self.game.do_action(action, team);
-            if self.game.rounds != round {
+            let seat = self.seat(team);
+            let action = self.agents[seat].choose_action(&self.game.view(team));
+            let (played, turn_over) = match self.game.do_action(action, team) {
+                Ok(outcome) => {
+                    self.replay.push(team, action, outcome.roll);
+                    (Some(action), outcome.turn_over)
+                },
+                Err(e) => {
+                    if self.verbose {
+                        println!("{} played {:?}: {}", team, action, e);
+                    }
+                    let played = match self.policy {
+                        IllegalActionPolicy::Forfeit => {
+                            self.game.forfeit(team);
+                            self.replay.push_forfeit(team, action);
+                            None
+                        },
+                        IllegalActionPolicy::Pass => {
+                            let pass = self.game.do_action(Action::Pass, team);
+                            self.replay.push(team, Action::Pass, pass.ok().and_then(|o| o.roll));
+                            Some(Action::Pass)
+                        },
+                        IllegalActionPolicy::Panic => panic!("illegal action"),
+                    };
+                    (played, true)
+                },
+            };
+            if let Some(action) = played {
+                for (agent, player) in self.agents.iter_mut().zip(self.game.get_players()) {
+                    let view = self.game.view(player.team);
+                    agent.on_action_observed(team, view.observe(team, action), &view);
+                }
+            }
+            if turn_over {
+                self.end_round(round, team);
+            }
+        }
 
-                println!("Round {}, team {}", round, team); // Debug
-                self.game.print_stats(team);                // purposes
+        for (agent, player) in self.agents.iter_mut().zip(self.game.get_players()) {
+            agent.on_game_end(&self.game.view(player.team));
+        }
+        self.end_game();
 
-                // Render frame
-                self.render(round);
+        if let Some(path) = &self.record {
+            if let Err(e) = self.replay.save(path) {
+                println!("Recording failed: {}", e);
             }
+        }
+
+        GameResult {
+            winners: self.game.get_winners(),
+            ending: self.game.get_ending().unwrap(),
+            rounds: self.game.rounds,
+            stats: self.game.get_players().iter().map(|p| (p.team, p.get_stats())).collect(),
+            series: self.series.clone(),
+        }
+    }
+
+    /// Plays a recorded game back, frames included. The handler must have
+    /// been built from `replay.start()`.
+    pub fn replay(&mut self, replay: &Replay) {
+        println!("Seed: {}", self.game.config.seed);
+        self.game.print_map();
+        for record in &replay.records {
+            let round = self.game.rounds;
 
-            // Win condition
-            if self.game.over { break; }
+            if record.forfeit {
+                self.game.forfeit(record.team);
+            } else if let Err(e) = self.game.do_action_rolled(record.action, record.team, record.roll.unwrap_or(0.0)) {
+                println!("Replay diverged: {} played {:?}: {}", record.team, record.action, e);
+                break;
+            }
+            if self.game.rounds != round {
+                self.end_round(round, record.team);
+            }
+        }
+        if self.game.over {
+            self.end_game();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::{GameConfig, Ending};
+
+    /// Sends gold to an enemy, with transfers off to boot.
+    fn cheat(_actions: Vec<Action>, _board: &Board, _player: &Player, _others: &[&Player]) -> Action {
+        Action::TransferGold(Team::Red)
+    }
+
+    fn pass(actions: Vec<Action>, _board: &Board, _player: &Player, _others: &[&Player]) -> Action {
+        *actions.last().unwrap()
+    }
+
+    fn cheated(policy: IllegalActionPolicy) -> GameResult {
+        let mut config = GameConfig::new(0);
+        config.max_rounds = Some(4);
+        let mut gh = GameHandler::with_agents(vec![Box::new(cheat), Box::new(pass)], Game::init(config));
+        gh.set_verbose(false);
+        gh.set_policy(policy);
+
+        gh.simulate()
+    }
+
+    #[test]
+    fn illegal_actions_forfeit() {
+        let result = cheated(IllegalActionPolicy::Forfeit);
+        assert_eq!(result.ending, Ending::Conquest);
+        assert_eq!(result.winners, vec![Team::Red]);
+    }
+
+    #[test]
+    fn illegal_actions_pass() {
+        let result = cheated(IllegalActionPolicy::Pass);
+        assert_eq!(result.ending, Ending::TurnLimit);
+        assert_eq!(result.rounds, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "illegal action")]
+    fn illegal_actions_panic() {
+        cheated(IllegalActionPolicy::Panic);
+    }
+}
@@ -0,0 +1,34 @@
+//! Agents play a seat through a whole game, keeping whatever they want to
+//! remember between turns.
+
+use super::game::{GameView, Action, Board, Player, Team};
+
+/// Builds a fresh agent for every game, so state never leaks from one game
+/// to the next.
+pub type AgentFactory = fn() -> Box<dyn Agent>;
+
+pub trait Agent {
+    /// Called once before the first turn.
+    fn on_game_start(&mut self, _view: &GameView) {}
+
+    /// Picks one of `view.actions()`.
+    fn choose_action(&mut self, view: &GameView) -> Action;
+
+    /// Called after every action played by any team, this agent's own
+    /// included. `action` is as `view.observe` shows it, None when the fog
+    /// of war hides what it was.
+    fn on_action_observed(&mut self, _team: Team, _action: Option<Action>, _view: &GameView) {}
+
+    /// Called once the game is over.
+    fn on_game_end(&mut self, _view: &GameView) {}
+}
+
+/// Plain IA functions and closures are stateless agents.
+impl<F> Agent for F
+where
+    F: Fn(Vec<Action>, &Board, &Player, &[&Player]) -> Action,
+{
+    fn choose_action(&mut self, view: &GameView) -> Action {
+        self(view.actions().to_vec(), view.board(), view.me(), &view.others())
+    }
+}
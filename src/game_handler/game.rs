@@ -1,100 +1,425 @@
+mod board;
+mod map;
+mod save;
+mod view;
+pub mod zobrist;
+pub mod undo;
+mod town_set;
+
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+pub use board::{Board, Topology};
+pub use map::MapError;
+pub use save::SaveError;
+pub use view::GameView;
+pub use town_set::TownSet;
+
+/// Random number generator used by the game. ChaCha is portable, so a seed
+/// always yields the same game no matter the platform or `rand` version.
+pub type GameRng = ChaCha8Rng;
 
 const INFLUENCE_PRICE: i32 = 2000;
 const GOLD_PRICE: i32 = 20;
+const GOLD_TRANSFER: i32 = 1000;
 fn specialty_point_cost(i: i32) -> i32 {
     100 * (i + 1).pow(2) + 150
 }
 
-/// Game instance.
-pub struct Game {
-    b_player: Player,
-    r_player: Player,
-    towns: Vec<Town>,
-    pub rng: ThreadRng,
+/// Game settings. The same config always produces the same game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub seed: u64,
+    pub topology: Topology,
+    pub width: usize,
+    pub height: usize,
+    /// Teams in turn order.
+    pub teams: Vec<Team>,
+    /// Starting capitals in turn order. Empty picks the board's defaults.
+    pub capitals: Vec<usize>,
+    /// Teams winning together. Teams left out play on their own.
+    pub alliances: Vec<Vec<Team>>,
+    /// Lets allies send each other gold.
+    pub gold_transfer: bool,
+    /// Turns played before the game is decided on points. None plays until
+    /// a single alliance is left.
+    #[serde(default)]
+    pub max_rounds: Option<usize>,
+    /// Actions a team may play in a turn before it's made to pass, which
+    /// counts as a turn like any other. None lets turns go on as long as
+    /// resources last.
+    #[serde(default)]
+    pub max_actions: Option<usize>,
+    /// Extra ways to win, on top of being the last alliance standing.
+    #[serde(default)]
+    pub victory: Vec<Victory>,
+    /// Fog of war: teams only see the stats of towns next to their
+    /// alliance's territory, and estimates of their enemies' resources.
+    #[serde(default)]
+    pub fog: bool,
+}
+
+/// Alternative victory conditions. The first player to meet one wins for
+/// its whole alliance.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Victory {
+    /// Hold this many towns.
+    Towns(usize),
+    /// Reach this much influence.
+    Influence(i32),
+    /// Own every town with this perk.
+    Perk(Perk),
+}
+
+impl GameConfig {
+    /// Config for the classic blue versus red game on a 4x4 grid.
+    pub fn new(seed: u64) -> GameConfig {
+        GameConfig {
+            seed,
+            topology: Topology::Grid,
+            width: 4,
+            height: 4,
+            teams: vec![Team::Blue, Team::Red],
+            capitals: vec![],
+            alliances: vec![],
+            gold_transfer: false,
+            max_rounds: None,
+            max_actions: None,
+            victory: vec![],
+            fog: false,
+        }
+    }
+}
+
+/// Game instance. Dice are drawn from `R`, so any `RngCore` can drive the
+/// rules: a seeded `StdRng`, a fixed-dice stub, a recorded stream...
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game<R: RngCore = GameRng> {
+    /// Players in turn order.
+    players: Vec<Player>,
+    board: Board,
+    pub rng: R,
+    pub config: GameConfig,
 
     pub over: bool,
     pub team: Team,
     pub rounds: usize,
+    /// Actions played in the current turn so far.
+    #[serde(default)]
+    pub turn_actions: usize,
+    /// Winning alliance once over, empty on a draw.
+    #[serde(default)]
+    winners: Vec<Team>,
+    #[serde(default)]
+    ending: Option<Ending>,
+    /// Silences the game's own printouts, e.g. in search trees.
+    #[serde(skip)]
+    pub quiet: bool,
+}
+
+/// Why a game ended.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Ending {
+    /// A single alliance is left standing.
+    Conquest,
+    /// A player met an alternative victory condition.
+    Victory(Victory),
+    /// The turn limit was reached, the game was decided on points.
+    TurnLimit,
 }
 
 impl Game {
-    pub fn init() -> Game {
-        let mut rng = rand::thread_rng();
+    pub fn init(config: GameConfig) -> Game {
+        let rng = dice_rng(config.seed);
+        Game::with_rng(config, rng)
+    }
+
+    /// Plays on `board`, rolling the dice from the config seed.
+    pub fn init_with_board(config: GameConfig, board: Board) -> Game {
+        let rng = dice_rng(config.seed);
+        Game::with_board(config, board, rng)
+    }
+
+    /// Plays on a map file, rolling the dice from the config seed.
+    pub fn from_map(config: GameConfig, path: &str) -> Result<Game, MapError> {
+        let board = map::load(path)?;
+        if board.starts() < config.teams.len() {
+            return Err(MapError::Invalid(format!("{} teams but only {} capitals", config.teams.len(), board.starts())));
+        }
+
+        Ok(Game::init_with_board(config, board))
+    }
+}
+
+/// The seed's stream 0 generates the map, stream 1 rolls the dice, and the
+/// following ones seed the agents, one per seat.
+fn dice_rng(seed: u64) -> GameRng {
+    let mut rng = GameRng::seed_from_u64(seed);
+    rng.set_stream(1);
+
+    rng
+}
 
-        let mut towns: Vec<Town> = vec![];
-        for id in 0..16usize {
-            towns.push(Town::new(&mut rng, id));
+fn agent_seed(seed: u64, seat: usize) -> u64 {
+    let mut rng = GameRng::seed_from_u64(seed);
+    rng.set_stream(2 + seat as u64);
+
+    rng.next_u64()
+}
+
+impl<R: RngCore> Game<R> {
+    /// Builds the map from the config seed and rolls the dice with `rng`.
+    pub fn with_rng(config: GameConfig, rng: R) -> Game<R> {
+        let mut map_rng = GameRng::seed_from_u64(config.seed);
+        let board = Board::gen(config.topology, config.width, config.height, config.teams.len(), &mut map_rng);
+
+        Game::with_board(config, board, rng)
+    }
+
+    /// Plays on a given board, e.g. a hand-authored one.
+    pub fn with_board(config: GameConfig, board: Board, rng: R) -> Game<R> {
+        assert!(config.teams.len() >= 2, "a game needs at least two teams");
+        for (i, team) in config.teams.iter().enumerate() {
+            assert!(!config.teams[..i].contains(team), "{} plays twice", team);
+        }
+
+        for (i, team) in config.alliances.concat().iter().enumerate() {
+            assert!(config.teams.contains(team), "{} is allied but not playing", team);
+            assert!(!config.alliances.concat()[..i].contains(team), "{} is in two alliances", team);
+        }
+
+        let mut players = vec![];
+        for (seat, &team) in config.teams.iter().enumerate() {
+            let capital = match config.capitals.get(seat) {
+                Some(&id) => board.get_town(id),
+                None => board.get_start(seat),
+            };
+            assert!(players.iter().all(|p: &Player| p.capital != capital), "two teams start on town {}", capital.id);
+
+            // Lone teams get an alliance of their own
+            let alliance = match config.alliances.iter().position(|a| a.contains(&team)) {
+                Some(alliance) => alliance,
+                None => config.alliances.len() + seat,
+            };
+            players.push(Player::new(team, alliance, capital));
         }
 
         Game {
-            b_player: Player::new(Team::Blue, &towns),
-            r_player: Player::new(Team::Red, &towns),
-            towns: towns,
+            team: config.teams[0],
+            players,
+            board,
             rng: rng,
-            
+            config,
+
             over: false,
-            team: Team::Blue,
             rounds: 0,
+            turn_actions: 0,
+            winners: vec![],
+            ending: None,
+            quiet: false,
         }
     }
 
-    pub fn get_towns(&self) -> &Vec<Town> {
-        &self.towns
+    pub fn get_board(&self) -> &Board {
+        &self.board
     }
 
-    pub fn get_player(&self, team: Team) -> &Player {
-        match team {
-            Team::Blue => &self.b_player,
-            Team::Red => &self.r_player,
+    /// Writes the board and starting capitals to a map file. Capitals are
+    /// the ones this game started on, then the board's other defaults.
+    pub fn save_map(&self, path: &str) -> Result<(), MapError> {
+        let mut capitals: Vec<usize> = (0..self.players.len()).map(|seat| match self.config.capitals.get(seat) {
+            Some(&id) => id,
+            None => self.board.get_start(seat).id,
+        }).collect();
+        for seat in 0..self.board.starts() {
+            let id = self.board.get_start(seat).id;
+            if capitals.len() < self.board.starts() && !capitals.contains(&id) {
+                capitals.push(id);
+            }
         }
+
+        map::save(&self.board, &capitals, path)
+    }
+
+    /// Writes the whole game state, dice included, to a save file.
+    pub fn save(&self, path: &str) -> Result<(), SaveError> where R: Serialize {
+        save::save(self, path)
+    }
+
+    /// Resumes a game written with `save`.
+    pub fn load(path: &str) -> Result<Game<R>, SaveError> where R: DeserializeOwned {
+        save::load(path)
     }
 
-    pub fn get_towns_id(&self, team: Team) -> Vec<usize> {
-        match team {
-            Team::Blue => self.b_player.get_towns_id(),
-            Team::Red => self.r_player.get_towns_id(),
+    fn seat(&self, team: Team) -> usize {
+        match self.players.iter().position(|p| p.team == team) {
+            Some(seat) => seat,
+            None => panic!("{} is not playing", team),
         }
     }
 
-    pub fn get_capital_id(&self, team: Team) -> usize {
-        match team {
-            Team::Blue => self.b_player.get_capital_id(),
-            Team::Red => self.r_player.get_capital_id(),
+    /// Players in turn order, eliminated ones included.
+    pub fn get_players(&self) -> &Vec<Player> {
+        &self.players
+    }
+
+    pub fn get_player(&self, team: Team) -> &Player {
+        &self.players[self.seat(team)]
+    }
+
+    /// Players still in the game other than `team`, allies included.
+    pub fn get_others(&self, team: Team) -> Vec<&Player> {
+        self.players.iter().filter(|p| p.team != team && p.is_alive()).collect()
+    }
+
+    /// Team owning town `id`, if any.
+    pub fn get_owner(&self, id: usize) -> Option<Team> {
+        self.players.iter().find(|p| p.owns(id)).map(|p| p.team)
+    }
+
+    /// Winning alliance, once the game is over. Empty on a draw.
+    pub fn get_winners(&self) -> Vec<Team> {
+        self.winners.clone()
+    }
+
+    /// What `team` gets to see of the game.
+    pub fn view(&self, team: Team) -> GameView {
+        GameView::new(self, team)
+    }
+
+    /// Why the game ended, once it's over.
+    pub fn get_ending(&self) -> Option<Ending> {
+        self.ending
+    }
+
+    /// Whether the game ended without a winner.
+    pub fn is_draw(&self) -> bool {
+        self.over && self.winners.is_empty()
+    }
+
+    fn check_over(&mut self) {
+        if self.over {
+            return;
+        }
+
+        // A single alliance is left standing
+        let mut alive = self.players.iter().filter(|p| p.is_alive());
+        let first = alive.next().map(|p| p.alliance);
+        if alive.all(|p| Some(p.alliance) == first) {
+            return self.win(first, Ending::Conquest);
+        }
+
+        // Alternative victories, checked in turn order
+        for player in self.players.iter().filter(|p| p.is_alive()) {
+            if let Some(&victory) = self.config.victory.iter().find(|&&v| self.meets(player, v)) {
+                if !self.quiet {
+                    println!("{} met a victory condition", player.team);
+                }
+                let alliance = player.alliance;
+                return self.win(Some(alliance), Ending::Victory(victory));
+            }
+        }
+
+        // Out of time: most towns, then most gold and influence
+        if self.config.max_rounds.is_some_and(|max| self.rounds >= max) {
+            if !self.quiet {
+                println!("Turn limit reached");
+            }
+            let mut best: Option<(usize, (usize, i32))> = None;
+            let mut tied = false;
+            for player in self.players.iter().filter(|p| p.is_alive()) {
+                let members = self.players.iter().filter(|p| p.is_alive() && p.alliance == player.alliance);
+                let score = members.fold((0, 0), |(towns, points), p| (towns + p.towns.len(), points + p.gold + p.influence));
+                match best {
+                    Some((alliance, _)) if alliance == player.alliance => {},
+                    Some((_, top)) if score < top => {},
+                    Some((_, top)) if score == top => tied = true,
+                    _ => {
+                        best = Some((player.alliance, score));
+                        tied = false;
+                    },
+                }
+            }
+
+            self.win(if tied { None } else { best.map(|(alliance, _)| alliance) }, Ending::TurnLimit);
         }
     }
 
-    fn pass_turn(&mut self) -> () {
-        // Win condition
-        if !(self.b_player.towns.contains(&self.b_player.capital))
-        || !(self.r_player.towns.contains(&self.r_player.capital))
-        {
+    /// Ends the game, `alliance` winning. None is a draw.
+    fn win(&mut self, alliance: Option<usize>, ending: Ending) {
+        self.over = true;
+        self.ending = Some(ending);
+        self.winners = self.players.iter()
+            .filter(|p| p.is_alive() && Some(p.alliance) == alliance)
+            .map(|p| p.team)
+            .collect();
+        if !self.quiet {
             println!("GG\'s");
-            self.over = true;
         }
+    }
 
-        // Pass turn normally otherwise
-        match self.team {
-            Team::Blue => self.b_player.end_turn(),
-            Team::Red => self.r_player.end_turn(),
+    fn meets(&self, player: &Player, victory: Victory) -> bool {
+        match victory {
+            Victory::Towns(n) => player.towns.len() >= n,
+            Victory::Influence(x) => player.influence >= x,
+            Victory::Perk(perk) => {
+                // Hidden towns could be of any perk
+                let mut towns = (0..self.board.len()).map(|id| self.board.get_town(id)).filter(|t| t.perk == perk || t.hidden).peekable();
+                towns.peek().is_some() && towns.all(|t| player.owns(t.id))
+            },
         }
+    }
+
+    fn pass_turn(&mut self) -> () {
+        // Players who lost their capital are out, their towns go free
+        for player in &mut self.players {
+            if player.is_alive() && !player.owns(player.capital.id) {
+                if !self.quiet {
+                    println!("{} is out", player.team);
+                }
+                player.towns.clear();
+            }
+        }
+
+        // Pass turn normally
+        let seat = self.seat(self.team);
+        self.players[seat].end_turn(&self.board);
 
-        self.team = self.team.rival();
+        let mut next = (seat + 1) % self.players.len();
+        while !self.players[next].is_alive() && next != seat {
+            next = (next + 1) % self.players.len();
+        }
+        self.team = self.players[next].team;
         self.rounds += 1;
+        self.turn_actions = 0;
+
+        self.check_over();
     }
 
     pub fn print_map(&self) -> () {
-        for x in 0..4 {
-            let mut v = [("", 0, 0); 4];
-            for i in 0..4 {
+        if self.board.topology == Topology::Planar || self.board.topology == Topology::Custom {
+            for id in 0..self.board.len() {
+                let town = self.board.get_town(id);
+                println!("{} {:?} -> {:?}", id, (town.perk, town.grade, town.size), self.board.get_neighbors(id));
+            }
+            return;
+        }
+
+        let w = self.board.width;
+        for x in 0..self.board.height {
+            let mut v = vec![("", 0, 0); w];
+            for i in 0..w {
+                let town = self.board.get_town(x*w + i);
                 v[i] = (
-                    match self.towns[x*4 + i].perk {
+                    match town.perk {
                         Perk::Flagellation => "F",
                         Perk::Communion => "C",
                         Perk::Display => "D",
                     },
-                    self.towns[x*4 + i].grade,
-                    self.towns[x*4 + i].size,
+                    town.grade,
+                    town.size,
                 );
             }
             println!("{:?}", v);
@@ -102,37 +427,195 @@ impl Game {
     }
 
     pub fn print_stats(&self, team: Team) -> () {
-        let stats: (i32, i32, i32, [i32; 3]) = match team {
-            Team::Blue => self.b_player.get_stats(),
-            Team::Red => self.r_player.get_stats(),
-        };
+        let stats: (i32, i32, i32, [i32; 3]) = self.get_player(team).get_stats();
 
         println!("gold: {}. Influence: {}. Available: {}. Points: {:?}", stats.0, stats.1, stats.2, stats.3);
     }
 
-    pub fn get_available_actions(&self, player: Team) -> Vec<Action> {
-        match player {
-            Team::Blue => self.b_player.get_available_actions(&self.towns, &self.r_player),
-            Team::Red => self.r_player.get_available_actions(&self.towns, &self.b_player),
+    pub fn get_available_actions(&self, team: Team) -> Vec<Action> {
+        let player = self.get_player(team);
+        let others = self.get_others(team);
+        let mut actions = player.get_available_actions(&self.board, &others);
+
+        // Action::TransferGold(Team)
+        if self.config.gold_transfer && player.can_aford(GOLD_TRANSFER, Resource::Gold) {
+            let pass = actions.pop();
+            for ally in others.iter().filter(|p| p.alliance == player.alliance) {
+                actions.push(Action::TransferGold(ally.team));
+            }
+            actions.extend(pass);
+        }
+
+        actions
+    }
+
+    /// Checks `action` against the rules before it's played.
+    pub fn check_action(&self, action: Action, team: Team) -> Result<(), IllegalAction> {
+        if self.over {
+            return Err(IllegalAction::GameOver);
+        }
+        if team != self.team || !self.players.iter().any(|p| p.team == team) {
+            return Err(IllegalAction::NotYourTurn(team));
+        }
+
+        let player = self.get_player(team);
+        let legal = match action {
+            Action::Pass => true,
+            Action::Convert(town) | Action::Capture(town) => {
+                self.check_town(town)?;
+                if player.owns(town.id) {
+                    return Err(IllegalAction::AlreadyOwned(town.id));
+                }
+                if !player.frontier(&self.board).contains(town.id) {
+                    return Err(IllegalAction::NotANeighbor(town.id));
+                }
+
+                let owner = self.get_owner(town.id).map(|owner| self.get_player(owner));
+                match (action, owner) {
+                    (Action::Convert(_), Some(_)) => return Err(IllegalAction::AlreadyOwned(town.id)),
+                    (Action::Convert(_), None) if self.board.get_town(town.id).hidden => return Err(IllegalAction::Hidden(town.id)),
+                    (Action::Convert(_), None) => town.grade <= player.get_specialty_points(town.perk),
+                    (_, Some(owner)) if owner.alliance != player.alliance => true,
+                    _ => return Err(IllegalAction::NotAnEnemyTown(town.id)),
+                }
+            },
+            Action::ChangeCapitalTo(town) => {
+                self.check_town(town)?;
+                if !player.owns(town.id) || town == player.capital {
+                    return Err(IllegalAction::NotOwned(town.id));
+                }
+                true
+            },
+            Action::AddSpecialtyPoint(_) => player.can_aford(1, Resource::SpecialtyPoint),
+            Action::BuySpecialtyPoint => player.can_aford(specialty_point_cost(player.specialty_points_bought), Resource::Gold),
+            Action::BuyInfluence => player.can_aford(INFLUENCE_PRICE, Resource::Gold),
+            Action::BuyGold => player.can_aford(GOLD_PRICE, Resource::Influence),
+            Action::TransferGold(to) => {
+                if !self.config.gold_transfer {
+                    return Err(IllegalAction::TransfersDisabled);
+                }
+                if !self.get_others(team).iter().any(|p| p.team == to && p.alliance == player.alliance) {
+                    return Err(IllegalAction::NotAnAlly(to));
+                }
+                player.can_aford(GOLD_TRANSFER, Resource::Gold)
+            },
+        };
+
+        if legal { Ok(()) } else { Err(IllegalAction::CantAfford) }
+    }
+
+    /// Towns in actions must be the board's, not a tampered copy.
+    fn check_town(&self, town: Town) -> Result<(), IllegalAction> {
+        if town.id >= self.board.len() {
+            return Err(IllegalAction::UnknownTown(town.id));
+        }
+
+        let real = self.board.get_town(town.id);
+        if real.perk != town.perk || real.grade != town.grade || real.size != town.size {
+            return Err(IllegalAction::UnknownTown(town.id));
+        }
+
+        Ok(())
+    }
+
+    /// Makes a team do an action, if legal.
+    pub fn do_action(&mut self, action: Action, team: Team) -> Result<TurnOutcome, IllegalAction> {
+        self.check_action(action, team)?;
+
+        let roll = match action {
+            Action::Capture(_) => self.rng.gen(),
+            _ => 0.0,
+        };
+        Ok(self.play_rolled(action, team, roll))
+    }
+
+    /// Same as `do_action`, with the capture dice already rolled.
+    pub fn do_action_rolled(&mut self, action: Action, team: Team, roll: f32) -> Result<TurnOutcome, IllegalAction> {
+        self.check_action(action, team)?;
+
+        Ok(self.play_rolled(action, team, roll))
+    }
+
+    /// Plays an action `check_action` already allowed.
+    fn play_rolled(&mut self, action: Action, team: Team, roll: f32) -> TurnOutcome {
+        let seat = self.seat(team);
+        let mut ends_turn = match action {
+            Action::Capture(town) => {
+                let owner = match self.get_owner(town.id) {
+                    Some(owner) => self.seat(owner),
+                    None => panic!("town {} has no owner to capture it from", town.id),
+                };
+                let (player, rival) = self.get_pair_mut(seat, owner);
+                player.capture(town, rival, roll)
+            },
+            Action::TransferGold(to) => {
+                let to = self.seat(to);
+                let (player, ally) = self.get_pair_mut(seat, to);
+                player.transfer_gold(ally)
+            },
+            _ => self.players[seat].do_action(action),
+        };
+
+        if !ends_turn {
+            self.turn_actions += 1;
+            if self.config.max_actions.is_some_and(|max| self.turn_actions >= max) {
+                if !self.quiet {
+                    println!("{} is out of actions", team);
+                }
+                ends_turn = true;
+            }
+        }
+
+        if ends_turn { self.pass_turn() }
+
+        TurnOutcome {
+            roll: match action {
+                Action::Capture(_) => Some(roll),
+                _ => None,
+            },
+            turn_over: ends_turn,
+        }
+    }
+
+    /// Takes `team` out of the game, its towns go free.
+    pub fn forfeit(&mut self, team: Team) {
+        let seat = self.seat(team);
+        if !self.quiet {
+            println!("{} forfeits", team);
+        }
+        self.players[seat].towns.clear();
+
+        if self.team == team && !self.over {
+            self.pass_turn();
+        } else {
+            self.check_over();
         }
     }
 
-    pub fn do_action(&mut self, action: Action, team: Team) -> () {
-        if match team {
-            Team::Blue => self.b_player.do_action(action, &mut self.r_player, &mut self.rng),
-            Team::Red => self.r_player.do_action(action, &mut self.b_player, &mut self.rng),
-        } { self.pass_turn() }
+    /// Borrows two different players at once.
+    fn get_pair_mut(&mut self, a: usize, b: usize) -> (&mut Player, &mut Player) {
+        if a < b {
+            let (x, y) = self.players.split_at_mut(b);
+            (&mut x[a], &mut y[0])
+        } else {
+            let (x, y) = self.players.split_at_mut(a);
+            (&mut y[0], &mut x[b])
+        }
     }
 }
 
-#[derive(Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 /// Player instance.
 pub struct Player {
+    pub team: Team,
+    /// Players sharing an alliance win together and can't capture each
+    /// other's towns.
+    pub alliance: usize,
     pub gold: i32,
     pub influence: i32,
     specialty: [i32; 3],
     available_specialty_points: i32,
-    towns: Vec<Town>,
+    towns: TownSet,
     capital: Town,
 
     zealous: i32,
@@ -140,18 +623,15 @@ pub struct Player {
 }
 
 impl Player {
-    pub fn new(team: Team, towns: &Vec<Town>) -> Player {
-        let starting_town = match team {
-            Team::Blue => towns[12],
-            Team::Red => towns[3],
-        };
-
+    pub fn new(team: Team, alliance: usize, starting_town: Town) -> Player {
         Player {
+            team,
+            alliance,
             gold: 0,
             influence: 0,
             specialty: [0; 3],
             available_specialty_points: 3,
-            towns: vec![starting_town],
+            towns: TownSet::from(vec![starting_town.id]),
             capital: starting_town,
 
             zealous: -1,
@@ -180,29 +660,36 @@ impl Player {
         self.specialty[Player::get_specialty_index(perk)]
     }
 
+    /// Ids of the player's towns, smallest first.
     pub fn get_towns_id(&self) -> Vec<usize> {
-        let mut ids = vec![];
-        for t in &self.towns {
-            ids.push(t.id);
-        }
-
-        return ids;
+        self.towns.iter().collect()
     }
 
     pub fn get_capital_id(&self) -> usize {
         self.capital.id
     }
 
-    pub fn get_neighbors(&self, towns: &Vec<Town>) -> Vec<Town> {
-        let mut neighbors = vec![];
-        for town in &self.towns {
-            for neighbor_id in town.get_neighbors() {
-                let neighbor = towns[neighbor_id];
-                if !(neighbors.contains(&neighbor)) && !(self.towns.contains(&neighbor)) {neighbors.push(neighbor)}
-            }
+    pub fn owns(&self, id: usize) -> bool {
+        self.towns.contains(id)
+    }
+
+    /// Eliminated players hold no towns.
+    pub fn is_alive(&self) -> bool {
+        !self.towns.is_empty()
+    }
+
+    /// Towns next to the player's, its own excluded.
+    pub fn frontier(&self, board: &Board) -> TownSet {
+        let mut frontier = TownSet::default();
+        for id in self.towns.iter() {
+            frontier = frontier.union(&board.get_neighbor_set(id));
         }
 
-        return neighbors;
+        frontier.difference(&self.towns)
+    }
+
+    pub fn get_neighbors(&self, board: &Board) -> Vec<Town> {
+        self.frontier(board).iter().map(|id| board.get_town(id)).collect()
     }
 
     fn can_aford(&self, price: i32, resource: Resource) -> bool {
@@ -215,9 +702,9 @@ impl Player {
     }
 
     /// Stuff that happens at the end of every turn
-    pub fn end_turn(&mut self) -> () {
+    pub fn end_turn(&mut self, board: &Board) {
         let mut gains: (i32, i32) = (0, 0);
-        for town in &self.towns {
+        for town in self.towns.iter().map(|id| board.get_town(id)) {
             gains.0 += self.capital.size * town.size * 150;
             gains.1 += self.capital.grade * town.grade * self.get_specialty_points(town.perk);
         }
@@ -227,24 +714,26 @@ impl Player {
     }
 
     /// Returns a list of actions a player is able to do.
-    pub fn get_available_actions(&self, towns: &Vec<Town>, rival: &Player) -> Vec<Action> {
+    pub fn get_available_actions(&self, board: &Board, others: &[&Player]) -> Vec<Action> {
         // Empty list of actions.
         let mut actions: Vec<Action> = vec![];
 
         // Action::Convert(Town)
         // Action::Capture(Town)
-        for neighbor in self.get_neighbors(towns) {
-            if rival.towns.contains(&neighbor) {
-                actions.push(Action::Capture(neighbor));
-            } else if neighbor.grade <= self.get_specialty_points(neighbor.perk) {
+        for neighbor in self.get_neighbors(board) {
+            if let Some(owner) = others.iter().find(|p| p.owns(neighbor.id)) {
+                if owner.alliance != self.alliance {
+                    actions.push(Action::Capture(neighbor));
+                }
+            } else if !neighbor.hidden && neighbor.grade <= self.get_specialty_points(neighbor.perk) {
                 actions.push(Action::Convert(neighbor));
             }
         }
 
         // Action::ChangeCapitalTo(Town)
-        for town in &self.towns {
-            if *town != self.capital {
-                actions.push(Action::ChangeCapitalTo(*town));
+        for id in self.towns.iter() {
+            if id != self.capital.id {
+                actions.push(Action::ChangeCapitalTo(board.get_town(id)));
             }
         }
 
@@ -277,21 +766,10 @@ impl Player {
         return actions;
     }
 
-    /// Makes a player do and action.
-    pub fn do_action(&mut self, action: Action, rival: &mut Player, rng: &mut ThreadRng) -> bool {
-        match action {
-            Action::Pass => true,
-            Action::Convert(town) => self.convert(town),
-            Action::ChangeCapitalTo(town) => self.change_capital_to(town),
-            Action::AddSpecialtyPoint(perk) => self.add_specialty_point(perk),
-            Action::BuySpecialtyPoint => self.buy_specialty_point(),
-            Action::Capture(town) => self.capture(town, rival, rng),
-            Action::BuyInfluence => self.buy_influence(),
-            Action::BuyGold => self.buy_gold(),
-        }
-    }
-
-    pub fn do_action2(&mut self, action: Action) -> bool {
+    /// Makes a player do and action. Captures and transfers need another
+    /// player, see `Player::capture` and `Player::transfer_gold`; here
+    /// captures only end the turn and transfers do nothing.
+    pub fn do_action(&mut self, action: Action) -> bool {
         match action {
             Action::Pass => true,
             Action::Convert(town) => self.convert(town),
@@ -301,11 +779,12 @@ impl Player {
             Action::Capture(_) => true,
             Action::BuyInfluence => self.buy_influence(),
             Action::BuyGold => self.buy_gold(),
+            Action::TransferGold(_) => false,
         }
     }
 
     fn convert(&mut self, town: Town) -> bool {
-        self.towns.push(town);
+        self.towns.insert(town.id);
 
         true
     }
@@ -326,23 +805,25 @@ impl Player {
     fn buy_specialty_point(&mut self) -> bool {
         let price = specialty_point_cost(self.specialty_points_bought);
         self.available_specialty_points += 1;
+        self.specialty_points_bought += 1;
         self.gold -= price;
 
         false
     }
 
-    fn capture(&mut self, town: Town, rival: &mut Player, rng: &mut ThreadRng) -> bool {
-        let c = |g: f32, i: f32| -> f32 {
-            let g = g * 11.0;
-            let i = (i+0.001) * 250.0;
+    /// Odds of capturing a town from `rival`: gold against influence.
+    pub fn capture_chance(&self, rival: &Player) -> f32 {
+        let g = self.gold as f32 * 11.0;
+        let i = (rival.influence as f32 + 0.001) * 250.0;
 
-            1.0 - ((g/i) + 1.0).powf(-1.0)
-        };
-        let d6: f32 = rng.gen();
+        1.0 - ((g/i) + 1.0).powf(-1.0)
+    }
 
-        if d6 < c(self.gold as f32, rival.influence as f32) {
-            rival.towns.retain(|&t| t != town);
-            self.towns.push(town);
+    /// Tries to take `town` from `rival`, `d6` being the dice roll.
+    pub fn capture(&mut self, town: Town, rival: &mut Player, d6: f32) -> bool {
+        if d6 < self.capture_chance(rival) {
+            rival.towns.remove(town.id);
+            self.towns.insert(town.id);
 
             rival.influence = 200;
             rival.zealous = 2;
@@ -354,6 +835,13 @@ impl Player {
         true
     }
 
+    pub fn transfer_gold(&mut self, ally: &mut Player) -> bool {
+        self.gold -= GOLD_TRANSFER;
+        ally.gold += GOLD_TRANSFER;
+
+        false
+    }
+
     fn buy_influence(&mut self) -> bool {
         self.gold -= INFLUENCE_PRICE;
         self.influence += 20;
@@ -370,12 +858,16 @@ impl Player {
 }
 
 /// Town instance.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Town {
     perk: Perk,
     pub grade: i32,
     pub size: i32,
     pub id: usize,
+    /// Hidden by the fog of war, so its perk and stats are guesses. Only
+    /// views have such towns.
+    #[serde(skip)]
+    hidden: bool,
 }
 
 impl PartialEq for Town {
@@ -385,7 +877,7 @@ impl PartialEq for Town {
 }
 
 impl Town {
-    pub fn new(rng: &mut ThreadRng, id: usize) -> Town {
+    pub fn new<R: RngCore + ?Sized>(rng: &mut R, id: usize) -> Town {
         let mut p = [Perk::Flagellation, Perk::Communion, Perk::Display];
         let mut s = [1, 1, 1, 2, 2, 3];
         let mut g = [1, 1, 2];
@@ -398,27 +890,33 @@ impl Town {
             size: s[0],
             grade: g[0],
             id: id,
+            hidden: false,
         }
     }
 
-    pub fn get_neighbors(&self) -> Vec<usize> {
-        let mut neighbors = vec![];
-        let id = self.id;
-        if id%4 != 0 {neighbors.push(id-1)}
-        if id%4 != 3 {neighbors.push(id+1)}
-        if !(id < 4) {neighbors.push(id-4)}
-        if !(id > 11) {neighbors.push(id+4)}
-
-        return neighbors;
+    /// What a town hidden by the fog of war looks like: the average grade
+    /// and size, rounded, and no telling its perk.
+    fn hidden(id: usize) -> Town {
+        Town {
+            perk: Perk::Flagellation,
+            size: 2,
+            grade: 1,
+            id,
+            hidden: true,
+        }
     }
 }
 
 // GLOBAL ENUMS
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Team {
-    Red,
     Blue,
+    Red,
+    Green,
+    Purple,
+    Orange,
+    Cyan,
 }
 
 enum Resource {
@@ -427,8 +925,36 @@ enum Resource {
     SpecialtyPoint,
 }
 
-/// Critical enum. Handles actions.
+/// What came out of a legal action.
 #[derive(Copy, Clone, Debug)]
+pub struct TurnOutcome {
+    /// Capture dice roll, only for `Action::Capture`.
+    pub roll: Option<f32>,
+    pub turn_over: bool,
+}
+
+/// Why an action was refused.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum IllegalAction {
+    GameOver,
+    NotYourTurn(Team),
+    /// The town isn't on the board, or its stats were tampered with.
+    UnknownTown(usize),
+    NotANeighbor(usize),
+    AlreadyOwned(usize),
+    NotAnEnemyTown(usize),
+    /// The town's grade and perk can't be seen, so there's no knowing
+    /// whether it can be converted.
+    Hidden(usize),
+    NotOwned(usize),
+    /// Not enough gold, influence, specialty points, or a too high grade.
+    CantAfford,
+    TransfersDisabled,
+    NotAnAlly(Team),
+}
+
+/// Critical enum. Handles actions.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Action {
     Pass,
     Convert(Town),
@@ -438,9 +964,10 @@ pub enum Action {
     Capture(Town),
     BuyInfluence,
     BuyGold,
+    TransferGold(Team),
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Perk {
     Flagellation,
     Communion,
@@ -448,20 +975,135 @@ pub enum Perk {
 }
 
 // misc
+impl std::fmt::Display for IllegalAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IllegalAction::GameOver => write!(f, "the game is over"),
+            IllegalAction::NotYourTurn(team) => write!(f, "it's not {}'s turn", team),
+            IllegalAction::UnknownTown(id) => write!(f, "town {} doesn't match the board", id),
+            IllegalAction::NotANeighbor(id) => write!(f, "town {} is out of reach", id),
+            IllegalAction::AlreadyOwned(id) => write!(f, "town {} is already owned", id),
+            IllegalAction::NotAnEnemyTown(id) => write!(f, "town {} isn't an enemy's", id),
+            IllegalAction::Hidden(id) => write!(f, "town {} is hidden by the fog of war", id),
+            IllegalAction::NotOwned(id) => write!(f, "town {} can't be the new capital", id),
+            IllegalAction::CantAfford => write!(f, "can't afford it"),
+            IllegalAction::TransfersDisabled => write!(f, "gold transfers are off"),
+            IllegalAction::NotAnAlly(team) => write!(f, "{} isn't an ally", team),
+        }
+    }
+}
+
 impl std::fmt::Display for Team {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", match self {
             Team::Blue => "BLUE",
             Team::Red => "RED",
+            Team::Green => "GREEN",
+            Team::Purple => "PURPLE",
+            Team::Orange => "ORANGE",
+            Team::Cyan => "CYAN",
         })
     }
 }
 
 impl Team {
-    pub fn rival(&self) -> Team {
-        match self {
-            Team::Blue => Team::Red,
-            _ => Team::Blue,
+    /// Every team, in the default turn order.
+    pub const ALL: [Team; 6] = [Team::Blue, Team::Red, Team::Green, Team::Purple, Team::Orange, Team::Cyan];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Towns in a row, teams starting on `starts` in turn order.
+    fn row(n: usize, starts: Vec<usize>, config: GameConfig) -> Game {
+        let towns = (0..n).map(|id| Town { perk: Perk::Communion, grade: 1, size: 1, id, hidden: false }).collect();
+        let positions = (0..n).map(|id| (id as f32 / n as f32, 0.5)).collect();
+        let edges = (0..n).map(|id| (0..n).filter(|&other| other.abs_diff(id) == 1).collect()).collect();
+        let board = Board::new(towns, positions, edges, starts).unwrap();
+
+        let mut game = Game::init_with_board(config, board);
+        game.quiet = true;
+        game
+    }
+
+    fn duel() -> Game {
+        row(4, vec![0, 3], GameConfig::new(0))
+    }
+
+    #[test]
+    fn check_action_says_why() {
+        let mut game = duel();
+        let town = |game: &Game, id| game.board.get_town(id);
+        let blue = Team::Blue;
+
+        assert_eq!(game.check_action(Action::Pass, Team::Red), Err(IllegalAction::NotYourTurn(Team::Red)));
+        assert_eq!(game.check_action(Action::Pass, Team::Green), Err(IllegalAction::NotYourTurn(Team::Green)));
+
+        let mut tampered = town(&game, 1);
+        tampered.grade = 0;
+        assert_eq!(game.check_action(Action::Convert(tampered), blue), Err(IllegalAction::UnknownTown(1)));
+        let mut missing = town(&game, 1);
+        missing.id = 4;
+        assert_eq!(game.check_action(Action::Convert(missing), blue), Err(IllegalAction::UnknownTown(4)));
+
+        assert_eq!(game.check_action(Action::Convert(town(&game, 0)), blue), Err(IllegalAction::AlreadyOwned(0)));
+        assert_eq!(game.check_action(Action::Convert(town(&game, 2)), blue), Err(IllegalAction::NotANeighbor(2)));
+        assert_eq!(game.check_action(Action::Capture(town(&game, 1)), blue), Err(IllegalAction::NotAnEnemyTown(1)));
+        assert_eq!(game.check_action(Action::ChangeCapitalTo(town(&game, 1)), blue), Err(IllegalAction::NotOwned(1)));
+        assert_eq!(game.check_action(Action::ChangeCapitalTo(town(&game, 0)), blue), Err(IllegalAction::NotOwned(0)));
+        assert_eq!(game.check_action(Action::Convert(town(&game, 1)), blue), Err(IllegalAction::CantAfford));
+        assert_eq!(game.check_action(Action::TransferGold(Team::Red), blue), Err(IllegalAction::TransfersDisabled));
+
+        game.config.gold_transfer = true;
+        assert_eq!(game.check_action(Action::TransferGold(Team::Red), blue), Err(IllegalAction::NotAnAlly(Team::Red)));
+
+        game.players[0].specialty = [1; 3];
+        assert_eq!(game.check_action(Action::Convert(town(&game, 1)), blue), Ok(()));
+        game.board.hide(1);
+        assert_eq!(game.check_action(Action::Convert(town(&game, 1)), blue), Err(IllegalAction::Hidden(1)));
+
+        game.over = true;
+        assert_eq!(game.check_action(Action::Pass, blue), Err(IllegalAction::GameOver));
+    }
+
+    /// Ends `game` on the turn limit, with these towns and gold per seat.
+    fn out_of_time(mut game: Game, holdings: &[(&[usize], i32)]) -> Game {
+        for (player, &(towns, gold)) in game.players.iter_mut().zip(holdings) {
+            player.towns = TownSet::from(towns.to_vec());
+            player.gold = gold;
         }
+        game.config.max_rounds = Some(game.rounds);
+        game.check_over();
+
+        assert_eq!(game.get_ending(), Some(Ending::TurnLimit));
+        game
+    }
+
+    #[test]
+    fn turn_limit_goes_by_towns_first() {
+        let game = out_of_time(duel(), &[(&[0, 1], 0), (&[3], 5000)]);
+        assert_eq!(game.get_winners(), vec![Team::Blue]);
+    }
+
+    #[test]
+    fn turn_limit_then_goes_by_gold_and_influence() {
+        let mut game = duel();
+        game.players[1].influence = 10;
+        let game = out_of_time(game, &[(&[0], 100), (&[3], 95)]);
+        assert_eq!(game.get_winners(), vec![Team::Red]);
+    }
+
+    #[test]
+    fn turn_limit_draws_between_tied_alliances() {
+        let game = out_of_time(duel(), &[(&[0], 100), (&[3], 100)]);
+        assert!(game.is_draw());
+
+        let mut config = GameConfig::new(0);
+        config.teams = vec![Team::Blue, Team::Red, Team::Green, Team::Purple];
+        config.alliances = vec![vec![Team::Blue, Team::Green], vec![Team::Red, Team::Purple]];
+        let game = row(6, vec![0, 5, 1, 4], config);
+        let game = out_of_time(game, &[(&[0], 300), (&[5, 3], 0), (&[1, 2], 0), (&[4], 300)]);
+        assert!(game.is_draw());
     }
 }
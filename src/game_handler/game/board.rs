@@ -0,0 +1,334 @@
+use rand::{Rng, RngCore};
+use serde::{Serialize, Deserialize};
+use super::{Town, TownSet};
+
+/// Shape of the generated town graph.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Topology {
+    /// Square grid, towns touch on their sides.
+    Grid,
+    /// Square grid, towns also touch on their corners.
+    Grid8,
+    /// Hexagonal grid, odd rows shifted half a town to the right.
+    Hex,
+    /// Towns scattered at random, linked by a planar (Gabriel) graph.
+    Planar,
+    /// Hand-authored map.
+    Custom,
+}
+
+/// Towns and the roads between them. Positions are in the unit square,
+/// (0, 0) being the top-left corner. Boards read from save files are checked
+/// the same way as hand-authored ones.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Layout", into = "Layout")]
+pub struct Board {
+    pub topology: Topology,
+    pub width: usize,
+    pub height: usize,
+    towns: Vec<Town>,
+    positions: Vec<(f32, f32)>,
+    edges: Vec<Vec<usize>>,
+    /// `edges` as sets, for working out frontiers a word at a time.
+    neighbors: Vec<TownSet>,
+    /// Starting capitals, in turn order.
+    starts: Vec<usize>,
+}
+
+/// A board as save files have it, before it's checked.
+#[derive(Clone, Serialize, Deserialize)]
+struct Layout {
+    topology: Topology,
+    width: usize,
+    height: usize,
+    towns: Vec<Town>,
+    positions: Vec<(f32, f32)>,
+    edges: Vec<Vec<usize>>,
+    starts: Vec<usize>,
+}
+
+/// Where the starting capitals go, in turn order: the four corners (blue on
+/// the bottom-left, red on the top-right), then the middle of the top and
+/// bottom sides.
+const START_SPOTS: [(f32, f32); 6] = [(0.0, 1.0), (1.0, 0.0), (0.0, 0.0), (1.0, 1.0), (0.5, 0.0), (0.5, 1.0)];
+
+impl Board {
+    /// Generates `width`x`height` towns linked according to `topology`, with
+    /// starting capitals for `players` teams.
+    pub fn gen<R: RngCore + ?Sized>(topology: Topology, width: usize, height: usize, players: usize, rng: &mut R) -> Board {
+        assert!(players <= START_SPOTS.len(), "at most {} teams can play", START_SPOTS.len());
+        assert!(width * height >= players.max(2), "a board needs a town per team");
+        assert!(width * height <= TownSet::MAX, "a board holds at most {} towns", TownSet::MAX);
+
+        let mut towns: Vec<Town> = vec![];
+        for id in 0..(width * height) {
+            towns.push(Town::new(rng, id));
+        }
+
+        let (positions, edges) = match topology {
+            Topology::Grid => grid(width, height, false, rng),
+            Topology::Grid8 => grid(width, height, true, rng),
+            Topology::Hex => hex(width, height, rng),
+            Topology::Planar => planar(width * height, rng),
+            Topology::Custom => panic!("custom boards are built with Board::new"),
+        };
+
+        let mut starts = vec![];
+        for &spot in &START_SPOTS[..players] {
+            starts.push(closest(&positions, spot, &starts));
+        }
+
+        Board {
+            topology,
+            width,
+            height,
+            towns,
+            positions,
+            neighbors: neighbor_sets(&edges),
+            edges,
+            starts,
+        }
+    }
+
+    /// Hand-authored board. `edges[id]` lists the towns linked to town `id`,
+    /// `starts` are the starting capitals in turn order.
+    pub fn new(towns: Vec<Town>, positions: Vec<(f32, f32)>, edges: Vec<Vec<usize>>, starts: Vec<usize>) -> Result<Board, String> {
+        Board::try_from(Layout {
+            topology: Topology::Custom,
+            width: towns.len(),
+            height: 1,
+            towns,
+            positions,
+            edges,
+            starts,
+        })
+    }
+
+    pub fn get_town(&self, id: usize) -> Town {
+        self.towns[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.towns.len()
+    }
+
+    /// Towns linked by a road to town `id`.
+    pub fn get_neighbors(&self, id: usize) -> &Vec<usize> {
+        &self.edges[id]
+    }
+
+    /// Same as `get_neighbors`, as a set.
+    pub fn get_neighbor_set(&self, id: usize) -> TownSet {
+        self.neighbors[id]
+    }
+
+    pub fn get_position(&self, id: usize) -> (f32, f32) {
+        self.positions[id]
+    }
+
+    /// Starting capital of the team playing `seat`-th.
+    pub fn get_start(&self, seat: usize) -> Town {
+        self.towns[self.starts[seat]]
+    }
+
+    /// Masks the stats of town `id`, for fog of war.
+    pub fn hide(&mut self, id: usize) {
+        self.towns[id] = Town::hidden(id);
+    }
+
+    /// Number of starting capitals, i.e. how many teams can play.
+    pub fn starts(&self) -> usize {
+        self.starts.len()
+    }
+}
+
+impl TryFrom<Layout> for Board {
+    type Error = String;
+
+    fn try_from(layout: Layout) -> Result<Board, String> {
+        let Layout { topology, width, height, towns, positions, edges, starts } = layout;
+
+        let n = towns.len();
+        if n < 2 {
+            return Err("a board needs at least two towns".to_string());
+        }
+        if n > TownSet::MAX {
+            return Err(format!("a board holds at most {} towns", TownSet::MAX));
+        }
+        if width * height != n {
+            return Err(format!("{} towns on a {}x{} board", n, width, height));
+        }
+        if positions.len() != n {
+            return Err(format!("{} towns but {} positions", n, positions.len()));
+        }
+        for (i, town) in towns.iter().enumerate() {
+            if town.id != i {
+                return Err(format!("town #{} has id {}", i, town.id));
+            }
+        }
+        if starts.len() < 2 {
+            return Err("a board needs at least two starting towns".to_string());
+        }
+        for (i, &start) in starts.iter().enumerate() {
+            if start >= n || starts[..i].contains(&start) {
+                return Err(format!("invalid starting towns {:?}", starts));
+            }
+        }
+
+        if edges.len() != n {
+            return Err(format!("{} towns but {} edge lists", n, edges.len()));
+        }
+        for (a, neighbors) in edges.iter().enumerate() {
+            for (i, &b) in neighbors.iter().enumerate() {
+                if b >= n || a == b || neighbors[..i].contains(&b) {
+                    return Err(format!("invalid road {} - {}", a, b));
+                }
+                if !edges[b].contains(&a) {
+                    return Err(format!("road {} - {} only goes one way", a, b));
+                }
+            }
+        }
+
+        Ok(Board {
+            topology,
+            width,
+            height,
+            towns,
+            positions,
+            neighbors: neighbor_sets(&edges),
+            edges,
+            starts,
+        })
+    }
+}
+
+impl From<Board> for Layout {
+    fn from(board: Board) -> Layout {
+        Layout {
+            topology: board.topology,
+            width: board.width,
+            height: board.height,
+            towns: board.towns,
+            positions: board.positions,
+            edges: board.edges,
+            starts: board.starts,
+        }
+    }
+}
+
+fn neighbor_sets(edges: &[Vec<usize>]) -> Vec<TownSet> {
+    edges.iter().map(|neighbors| TownSet::from(neighbors.clone())).collect()
+}
+
+/// Random offset keeping a town within `spread` of its slot.
+fn jitter<R: RngCore + ?Sized>(rng: &mut R, spread: f32) -> f32 {
+    (rng.gen::<f32>() - 0.5) * spread
+}
+
+fn grid<R: RngCore + ?Sized>(width: usize, height: usize, diagonals: bool, rng: &mut R) -> (Vec<(f32, f32)>, Vec<Vec<usize>>) {
+    let mut positions = vec![];
+    let mut edges = vec![];
+    let cell_w = 1.0 / width as f32;
+    let cell_h = 1.0 / height as f32;
+
+    for id in 0..(width * height) {
+        let (x, y) = ((id % width) as i32, (id / width) as i32);
+        positions.push((
+            (x as f32 + 0.5) * cell_w + jitter(rng, 0.6 * cell_w),
+            (y as f32 + 0.5) * cell_h + jitter(rng, 0.6 * cell_h),
+        ));
+
+        let mut neighbors = vec![];
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)] {
+            if !diagonals && dx != 0 && dy != 0 {
+                continue;
+            }
+            let (nx, ny) = (x + dx, y + dy);
+            if nx >= 0 && ny >= 0 && nx < width as i32 && ny < height as i32 {
+                neighbors.push(nx as usize + ny as usize * width);
+            }
+        }
+        edges.push(neighbors);
+    }
+
+    (positions, edges)
+}
+
+fn hex<R: RngCore + ?Sized>(width: usize, height: usize, rng: &mut R) -> (Vec<(f32, f32)>, Vec<Vec<usize>>) {
+    let mut positions = vec![];
+    let mut edges = vec![];
+    let cell_w = 1.0 / (width as f32 + 0.5);
+    let cell_h = 1.0 / height as f32;
+
+    for id in 0..(width * height) {
+        let (x, y) = ((id % width) as i32, (id / width) as i32);
+        let shift = if y % 2 == 1 {0.5} else {0.0};
+        positions.push((
+            (x as f32 + 0.5 + shift) * cell_w + jitter(rng, 0.3 * cell_w),
+            (y as f32 + 0.5) * cell_h + jitter(rng, 0.3 * cell_h),
+        ));
+
+        // Rows above and below reach one column towards the shift
+        let side = if y % 2 == 1 {1} else {-1};
+        let mut neighbors = vec![];
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (side, -1), (0, 1), (side, 1)] {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx >= 0 && ny >= 0 && nx < width as i32 && ny < height as i32 {
+                neighbors.push(nx as usize + ny as usize * width);
+            }
+        }
+        edges.push(neighbors);
+    }
+
+    (positions, edges)
+}
+
+fn planar<R: RngCore + ?Sized>(n: usize, rng: &mut R) -> (Vec<(f32, f32)>, Vec<Vec<usize>>) {
+    // Scatter towns, keeping them apart so their sprites don't overlap
+    let min_dist = 0.5 / (n as f32).sqrt();
+    let mut positions: Vec<(f32, f32)> = vec![];
+    let mut attempts = 0;
+    while positions.len() < n {
+        let p = (0.05 + 0.9 * rng.gen::<f32>(), 0.05 + 0.9 * rng.gen::<f32>());
+        attempts += 1;
+        if attempts > 1000 || positions.iter().all(|&q| dist2(p, q) >= min_dist * min_dist) {
+            positions.push(p);
+            attempts = 0;
+        }
+    }
+
+    // Gabriel graph: a and b are linked unless another town lies inside the
+    // circle of diameter ab. It is planar and connected.
+    let mut edges = vec![vec![]; n];
+    for a in 0..n {
+        for b in (a + 1)..n {
+            let (pa, pb) = (positions[a], positions[b]);
+            let mid = ((pa.0 + pb.0) / 2.0, (pa.1 + pb.1) / 2.0);
+            let r2 = dist2(pa, pb) / 4.0;
+            if (0..n).all(|c| c == a || c == b || dist2(positions[c], mid) >= r2) {
+                edges[a].push(b);
+                edges[b].push(a);
+            }
+        }
+    }
+
+    (positions, edges)
+}
+
+fn dist2(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+fn closest(positions: &[(f32, f32)], target: (f32, f32), skip: &[usize]) -> usize {
+    let mut best: Option<usize> = None;
+    for (i, &p) in positions.iter().enumerate() {
+        if skip.contains(&i) {
+            continue;
+        }
+        if best.is_none_or(|b| dist2(p, target) < dist2(positions[b], target)) {
+            best = Some(i);
+        }
+    }
+
+    best.unwrap()
+}
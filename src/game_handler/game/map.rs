@@ -0,0 +1,107 @@
+//! Map files: boards written as JSON so scenarios can be hand-tuned.
+//!
+//! ```json
+//! {
+//!   "towns": [
+//!     { "perk": "Communion", "grade": 1, "size": 2, "position": [0.1, 0.9], "edges": [1] },
+//!     { "perk": "Display", "grade": 2, "size": 1, "position": [0.9, 0.1], "edges": [0] }
+//!   ],
+//!   "capitals": [0, 1]
+//! }
+//! ```
+//!
+//! Positions are in the unit square, (0, 0) being the top-left corner.
+//! Capitals are listed in turn order, blue then red by default.
+
+use std::fs;
+use serde::{Serialize, Deserialize};
+use super::{Board, Town, Perk};
+
+#[derive(Serialize, Deserialize)]
+struct TownEntry {
+    perk: Perk,
+    grade: i32,
+    size: i32,
+    position: (f32, f32),
+    edges: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MapFile {
+    towns: Vec<TownEntry>,
+    capitals: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(std::io::Error),
+    Syntax(serde_json::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::Io(e) => write!(f, "can't access map file: {}", e),
+            MapError::Syntax(e) => write!(f, "malformed map file: {}", e),
+            MapError::Invalid(e) => write!(f, "invalid map: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<std::io::Error> for MapError {
+    fn from(e: std::io::Error) -> MapError {
+        MapError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for MapError {
+    fn from(e: serde_json::Error) -> MapError {
+        MapError::Syntax(e)
+    }
+}
+
+pub fn load(path: &str) -> Result<Board, MapError> {
+    let file: MapFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    let mut towns = vec![];
+    let mut positions = vec![];
+    let mut edges = vec![];
+    for (id, entry) in file.towns.iter().enumerate() {
+        towns.push(Town {
+            perk: entry.perk,
+            grade: entry.grade,
+            size: entry.size,
+            id,
+            hidden: false,
+        });
+        positions.push(entry.position);
+        edges.push(entry.edges.clone());
+    }
+
+    Board::new(towns, positions, edges, file.capitals).map_err(MapError::Invalid)
+}
+
+pub fn save(board: &Board, capitals: &[usize], path: &str) -> Result<(), MapError> {
+    let mut towns = vec![];
+    for id in 0..board.len() {
+        let town = board.get_town(id);
+        towns.push(TownEntry {
+            perk: town.perk,
+            grade: town.grade,
+            size: town.size,
+            position: board.get_position(id),
+            edges: board.get_neighbors(id).clone(),
+        });
+    }
+
+    let file = MapFile {
+        towns,
+        capitals: capitals.to_vec(),
+    };
+    fs::write(path, serde_json::to_string_pretty(&file)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Save files: the complete game state, dice included, written as JSON so
+//! games can be resumed, forked and diffed.
+
+use std::fs;
+use rand::RngCore;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use super::Game;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Syntax(serde_json::Error),
+    /// Parses, but isn't a game that can be played on.
+    Invalid(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "can't access save file: {}", e),
+            SaveError::Syntax(e) => write!(f, "malformed save file: {}", e),
+            SaveError::Invalid(e) => write!(f, "invalid save file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> SaveError {
+        SaveError::Syntax(e)
+    }
+}
+
+pub fn load<R: RngCore + DeserializeOwned>(path: &str) -> Result<Game<R>, SaveError> {
+    let game: Game<R> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    check(&game).map_err(SaveError::Invalid)?;
+
+    Ok(game)
+}
+
+/// Boards check themselves as they're read, players must fit on theirs.
+fn check<R: RngCore>(game: &Game<R>) -> Result<(), String> {
+    let n = game.board.len();
+    if !game.players.iter().any(|p| p.team == game.team) {
+        return Err(format!("it's {}'s turn but {} isn't playing", game.team, game.team));
+    }
+    for player in &game.players {
+        let capital = player.capital;
+        if capital.id >= n {
+            return Err(format!("{}'s capital {} isn't on the board", player.team, capital.id));
+        }
+        let real = game.board.get_town(capital.id);
+        if (real.perk, real.grade, real.size) != (capital.perk, capital.grade, capital.size) {
+            return Err(format!("{}'s capital {} doesn't match the board", player.team, capital.id));
+        }
+        if let Some(id) = player.towns.iter().find(|&id| id >= n) {
+            return Err(format!("{} owns town {}, which isn't on the board", player.team, id));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save<R: RngCore + Serialize>(game: &Game<R>, path: &str) -> Result<(), SaveError> {
+    fs::write(path, serde_json::to_string_pretty(game)?)?;
+
+    Ok(())
+}
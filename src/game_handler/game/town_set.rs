@@ -0,0 +1,138 @@
+//! Sets of towns as bitsets: checking, adding or taking a town is a single
+//! bit operation, and sets merge a word at a time.
+
+use serde::{Serialize, Deserialize};
+
+/// Set of town ids below `TownSet::MAX`. Saved as a list of ids.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+#[serde(from = "Vec<usize>", into = "Vec<usize>")]
+pub struct TownSet {
+    bits: [u64; 4],
+}
+
+impl TownSet {
+    /// Boards can't have more towns than this.
+    pub const MAX: usize = 256;
+
+    pub fn contains(&self, id: usize) -> bool {
+        id < TownSet::MAX && self.bits[id / 64] & (1 << (id % 64)) != 0
+    }
+
+    pub fn insert(&mut self, id: usize) {
+        debug_assert!(id < TownSet::MAX, "town {} is past the last one a set holds", id);
+        self.bits[id / 64] |= 1 << (id % 64);
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        debug_assert!(id < TownSet::MAX, "town {} is past the last one a set holds", id);
+        self.bits[id / 64] &= !(1 << (id % 64));
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = [0; 4];
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == [0; 4]
+    }
+
+    /// Towns in `self`, `other` or both.
+    pub fn union(&self, other: &TownSet) -> TownSet {
+        let mut bits = self.bits;
+        for (word, other) in bits.iter_mut().zip(other.bits) {
+            *word |= other;
+        }
+
+        TownSet { bits }
+    }
+
+    /// Towns in `self` and not in `other`.
+    pub fn difference(&self, other: &TownSet) -> TownSet {
+        let mut bits = self.bits;
+        for (word, other) in bits.iter_mut().zip(other.bits) {
+            *word &= !other;
+        }
+
+        TownSet { bits }
+    }
+
+    /// Town ids, smallest first.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+impl From<Vec<usize>> for TownSet {
+    fn from(ids: Vec<usize>) -> TownSet {
+        let mut set = TownSet::default();
+        for id in ids {
+            set.insert(id);
+        }
+
+        set
+    }
+}
+
+impl From<TownSet> for Vec<usize> {
+    fn from(set: TownSet) -> Vec<usize> {
+        set.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TownSet;
+
+    #[test]
+    fn iterates_in_order_across_words() {
+        let set = TownSet::from(vec![200, 64, 0, 63, 65, 255, 128]);
+
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 63, 64, 65, 128, 200, 255]);
+        assert_eq!(set.len(), 7);
+        assert!(set.contains(63) && set.contains(64) && !set.contains(62));
+        assert!(!set.contains(TownSet::MAX));
+    }
+
+    #[test]
+    fn difference_keeps_towns_not_in_other() {
+        let a = TownSet::from(vec![1, 63, 64, 130]);
+        let b = TownSet::from(vec![63, 130, 131]);
+
+        assert_eq!(Vec::from(a.difference(&b)), vec![1, 64]);
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn union_keeps_towns_in_either() {
+        let a = TownSet::from(vec![1, 64, 200]);
+        let b = TownSet::from(vec![1, 2, 255]);
+
+        assert_eq!(Vec::from(a.union(&b)), vec![1, 2, 64, 200, 255]);
+    }
+
+    #[test]
+    fn saves_as_a_list_of_ids() {
+        let mut set = TownSet::from(vec![3, 70]);
+        set.remove(3);
+        set.insert(5);
+
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, "[5,70]");
+        assert_eq!(serde_json::from_str::<TownSet>(&json).unwrap(), set);
+    }
+}
@@ -0,0 +1,97 @@
+//! Playing actions in place and taking them back, so searches don't clone
+//! the whole game at every node.
+
+use rand::RngCore;
+use super::{Game, Player, Team, Action, Ending, IllegalAction};
+
+/// What `Game::apply` changed, for `Game::undo` to put back. Players are
+/// small copies, and only the ones an action touches are saved.
+pub struct Undo {
+    team: Team,
+    rounds: usize,
+    turn_actions: usize,
+    over: bool,
+    winners: Vec<Team>,
+    ending: Option<Ending>,
+    /// Seat that played, and how it was.
+    player: (usize, Player),
+    /// The other player involved, if any: the one captured from, who can
+    /// be knocked out, or the ally sent gold.
+    other: Option<(usize, Player)>,
+}
+
+impl<R: RngCore> Game<R> {
+    /// Plays `action` for the team whose turn it is, `roll` being the
+    /// capture dice. The game stays as it was if the action is illegal.
+    pub fn apply(&mut self, action: Action, roll: f32) -> Result<Undo, IllegalAction> {
+        let team = self.team;
+        self.check_action(action, team)?;
+
+        let seat = self.seat(team);
+        let other = match action {
+            Action::Capture(town) => self.get_owner(town.id).map(|owner| self.seat(owner)),
+            Action::TransferGold(to) => Some(self.seat(to)),
+            _ => None,
+        };
+        let undo = Undo {
+            team,
+            rounds: self.rounds,
+            turn_actions: self.turn_actions,
+            over: self.over,
+            winners: self.winners.clone(),
+            ending: self.ending,
+            player: (seat, self.players[seat]),
+            other: other.map(|seat| (seat, self.players[seat])),
+        };
+
+        self.play_rolled(action, team, roll);
+
+        Ok(undo)
+    }
+
+    /// Takes back the last action `apply` played.
+    pub fn undo(&mut self, undo: Undo) {
+        let (seat, player) = undo.player;
+        self.players[seat] = player;
+        if let Some((seat, player)) = undo.other {
+            self.players[seat] = player;
+        }
+
+        self.team = undo.team;
+        self.rounds = undo.rounds;
+        self.turn_actions = undo.turn_actions;
+        self.over = undo.over;
+        self.winners = undo.winners;
+        self.ending = undo.ending;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::seq::SliceRandom;
+    use super::super::{Game, GameConfig, GameRng};
+
+    #[test]
+    fn undo_restores_the_game() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for seed in 0..20 {
+            let mut config = GameConfig::new(seed);
+            config.max_rounds = Some(60);
+            let mut game = Game::init(config);
+            game.quiet = true;
+
+            while !game.over {
+                let before = (serde_json::to_string(&game).unwrap(), game.zobrist());
+                for action in game.get_available_actions(game.team) {
+                    let undo = game.apply(action, rng.gen()).unwrap();
+                    game.undo(undo);
+                    assert_eq!((serde_json::to_string(&game).unwrap(), game.zobrist()), before, "{:?}", action);
+                }
+
+                let action = *game.get_available_actions(game.team).choose(&mut rng).unwrap();
+                game.apply(action, rng.gen()).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! What a team gets to see of the game when picking an action.
+
+use std::sync::Arc;
+use rand::RngCore;
+use super::{Game, GameConfig, Board, Player, Action, Team, dice_rng, agent_seed};
+
+/// Enemy resources are only known to the nearest multiple of these under fog
+/// of war.
+const GOLD_ESTIMATE: i32 = 1000;
+const INFLUENCE_ESTIMATE: i32 = 10;
+/// Seed views show instead of the real one, which would give away the board
+/// under the fog and every dice roll to come.
+const VIEW_SEED: u64 = 0;
+
+/// Read-only snapshot of a game from one team's seat. Clones share the
+/// board and players, so keeping views around between turns is cheap.
+#[derive(Clone)]
+pub struct GameView {
+    team: Team,
+    turn: Team,
+    rounds: usize,
+    /// Actions played in the current turn so far.
+    turn_actions: usize,
+    agent_seed: u64,
+    config: Arc<GameConfig>,
+    board: Arc<Board>,
+    /// Towns whose stats `team` can see.
+    visible: Arc<Vec<bool>>,
+    /// Players in turn order, eliminated ones included.
+    players: Arc<Vec<Player>>,
+    /// What `team` can do right now, empty when it's not its turn.
+    actions: Arc<Vec<Action>>,
+}
+
+impl GameView {
+    pub fn new<R: RngCore>(game: &Game<R>, team: Team) -> GameView {
+        let actions = if game.team == team && !game.over {
+            game.get_available_actions(team)
+        } else {
+            vec![]
+        };
+
+        let mut board = game.get_board().clone();
+        let mut players = game.get_players().clone();
+        let mut visible = vec![true; board.len()];
+        if game.config.fog {
+            // Allies share what they see
+            let alliance = game.get_player(team).alliance;
+            let sight: Vec<&Player> = players.iter().filter(|p| p.alliance == alliance).collect();
+            for (id, seen) in visible.iter_mut().enumerate() {
+                *seen = sight.iter().any(|p| p.owns(id) || board.get_neighbors(id).iter().any(|&n| p.owns(n)));
+            }
+
+            for id in (0..board.len()).filter(|&id| !visible[id]) {
+                board.hide(id);
+            }
+            for player in players.iter_mut().filter(|p| p.alliance != alliance) {
+                fog(player, &board);
+            }
+        }
+
+        let mut config = game.config.clone();
+        config.seed = VIEW_SEED;
+
+        GameView {
+            team,
+            turn: game.team,
+            rounds: game.rounds,
+            turn_actions: game.turn_actions,
+            agent_seed: agent_seed(game.config.seed, game.seat(team)),
+            config: Arc::new(config),
+            board: Arc::new(board),
+            visible: Arc::new(visible),
+            players: Arc::new(players),
+            actions: Arc::new(actions),
+        }
+    }
+
+    /// Team this view is for.
+    pub fn team(&self) -> Team {
+        self.team
+    }
+
+    /// Team whose turn it is.
+    pub fn turn(&self) -> Team {
+        self.turn
+    }
+
+    /// Seed for this team's agent to draw from. It comes from the game's
+    /// seed and the team's seat, so games play the same again from their
+    /// own seed, yet tells nothing of the board or the dice.
+    pub fn agent_seed(&self) -> u64 {
+        self.agent_seed
+    }
+
+    /// Turns played so far.
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    /// Turns played before the game is decided on points, if limited.
+    pub fn max_rounds(&self) -> Option<usize> {
+        self.config.max_rounds
+    }
+
+    /// The board, stats of towns hidden by the fog of war masked.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Whether each town's stats can be seen, by id.
+    pub fn visible(&self) -> &[bool] {
+        &self.visible
+    }
+
+    /// Players in turn order, eliminated ones included. Under fog of war,
+    /// enemies' gold and influence are rounded and their specialties hidden.
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    /// The player this view is for.
+    pub fn me(&self) -> &Player {
+        self.players.iter().find(|p| p.team == self.team).unwrap()
+    }
+
+    /// Players still in the game other than this view's, allies included.
+    pub fn others(&self) -> Vec<&Player> {
+        self.players.iter().filter(|p| p.team != self.team && p.is_alive()).collect()
+    }
+
+    /// Legal actions left this turn, passing always last.
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// What this view's team gets to see of `team` playing `action`. Under
+    /// fog of war, enemies' actions show hidden towns masked, and their
+    /// specialty points and gold transfers only show as None: something
+    /// was done.
+    pub fn observe(&self, team: Team, action: Action) -> Option<Action> {
+        let alliance = |team: Team| self.players.iter().find(|p| p.team == team).unwrap().alliance;
+        if !self.config.fog || alliance(team) == alliance(self.team) {
+            return Some(action);
+        }
+
+        match action {
+            Action::Convert(town) => Some(Action::Convert(self.board.get_town(town.id))),
+            Action::ChangeCapitalTo(town) => Some(Action::ChangeCapitalTo(self.board.get_town(town.id))),
+            Action::Capture(town) => Some(Action::Capture(self.board.get_town(town.id))),
+            Action::AddSpecialtyPoint(_) | Action::BuySpecialtyPoint | Action::TransferGold(_) => None,
+            Action::Pass | Action::BuyInfluence | Action::BuyGold => Some(action),
+        }
+    }
+
+    /// The game as this view's team believes it to be, fog included, for
+    /// searching ahead. Its dice have nothing to do with the real ones. It
+    /// prints nothing.
+    pub fn to_game(&self) -> Game {
+        Game {
+            players: self.players.to_vec(),
+            board: self.board.as_ref().clone(),
+            rng: dice_rng(self.config.seed),
+            config: self.config.as_ref().clone(),
+            over: false,
+            team: self.turn,
+            rounds: self.rounds,
+            turn_actions: self.turn_actions,
+            winners: vec![],
+            ending: None,
+            quiet: true,
+        }
+    }
+}
+
+/// Blurs what an enemy shows through the fog of war.
+fn fog(player: &mut Player, board: &Board) {
+    player.gold = estimate(player.gold, GOLD_ESTIMATE);
+    player.influence = estimate(player.influence, INFLUENCE_ESTIMATE);
+    player.specialty = [0; 3];
+    player.available_specialty_points = 0;
+    player.specialty_points_bought = 0;
+    player.zealous = -1;
+    player.capital = board.get_town(player.capital.id);
+}
+
+fn estimate(x: i32, step: i32) -> i32 {
+    (x + step / 2).div_euclid(step) * step
+}
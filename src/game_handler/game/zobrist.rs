@@ -0,0 +1,75 @@
+//! Zobrist-style hashes of game states, for transposition tables. Every
+//! feature of a state (a town held, a team's gold...) gets a pseudo-random
+//! key, and a state hashes to the XOR of its features' keys, so states
+//! reached through different action orders hash the same. Hashes are worked
+//! out afresh from every feature on each call, not kept up to date.
+
+use rand::RngCore;
+use super::{Game, Player};
+
+const TOWN: u64 = 1;
+const CAPITAL: u64 = 2;
+const GOLD: u64 = 3;
+const INFLUENCE: u64 = 4;
+const SPECIALTY: u64 = 5;
+const AVAILABLE: u64 = 6;
+const BOUGHT: u64 = 7;
+const ZEALOUS: u64 = 8;
+const TURN: u64 = 9;
+const OVER: u64 = 10;
+
+/// SplitMix64's finalizer: a cheap bijection that scrambles every bit.
+fn scramble(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+    x ^ (x >> 31)
+}
+
+/// Key of `feature` having `value` for the player in `seat`. Keys are
+/// computed rather than drawn from tables, so they're the same in every
+/// game and there's nothing to set up.
+fn key(feature: u64, seat: u64, value: i64) -> u64 {
+    scramble(scramble(scramble(feature) ^ seat) ^ value as u64)
+}
+
+/// Folds `x` into `hash`, e.g. the search depth a score was found at.
+pub fn mix(hash: u64, x: u64) -> u64 {
+    scramble(hash ^ scramble(x))
+}
+
+impl Player {
+    /// Hash of everything the player holds.
+    pub fn zobrist(&self) -> u64 {
+        let seat = self.team as u64;
+        let mut hash = key(CAPITAL, seat, self.capital.id as i64)
+            ^ key(GOLD, seat, self.gold as i64)
+            ^ key(INFLUENCE, seat, self.influence as i64)
+            ^ key(AVAILABLE, seat, self.available_specialty_points as i64)
+            ^ key(BOUGHT, seat, self.specialty_points_bought as i64)
+            ^ key(ZEALOUS, seat, self.zealous as i64);
+        for id in self.towns.iter() {
+            hash ^= key(TOWN, seat, id as i64);
+        }
+        for (i, &points) in self.specialty.iter().enumerate() {
+            hash ^= key(SPECIALTY, seat, ((i as i64) << 32) + points as i64);
+        }
+
+        hash
+    }
+}
+
+impl<R: RngCore> Game<R> {
+    /// Hash of the players and whose turn it is. The round count is left
+    /// out so positions match from one turn to the next, and so are the
+    /// board and the dice, which searches don't change. Searches that can
+    /// reach the turn limit must tell positions apart by rounds left.
+    pub fn zobrist(&self) -> u64 {
+        let mut hash = key(TURN, self.team as u64, 0) ^ key(OVER, 0, self.over as i64);
+        for player in &self.players {
+            hash ^= player.zobrist();
+        }
+
+        hash
+    }
+}
@@ -0,0 +1,72 @@
+//! Observers get notified as a game unfolds, e.g. to draw it. A handler
+//! without any runs headless.
+
+use image::{RgbaImage, DynamicImage, open};
+use image::imageops::overlay;
+use super::renderer::Renderer;
+use super::game::{Game, Team};
+
+pub trait Observer {
+    /// Called once `team` ended its turn, `round` being the turn that ended.
+    fn on_round(&mut self, round: usize, team: Team, game: &Game);
+
+    /// Called once the game is over.
+    fn on_game_end(&mut self, _game: &Game) {}
+}
+
+/// Writes a PNG frame of the map to `src/assets` every round.
+pub struct Frames {
+    town_spr: DynamicImage,
+    renderer: Renderer,
+    /// Team whose view of the game is drawn, everything is shown otherwise.
+    perspective: Option<Team>,
+}
+
+impl Frames {
+    /// Draws the game as seen by `team`, fog of war included.
+    pub fn with_perspective(game: &Game, team: Team) -> Frames {
+        let mut frames = Frames::new(game);
+        frames.perspective = Some(team);
+
+        frames
+    }
+
+    /// Only prints that it's starting if `game` isn't quiet.
+    pub fn new(game: &Game) -> Frames {
+        if !game.quiet {
+            println!("Initializing renderer [2/2]");
+        }
+        let renderer = Renderer::init(game.config.seed, game.get_board());
+
+        let town_spr = match open("src/assets/town.png") {
+            Ok(spr) => spr,
+            Err(_) => panic!()
+        };
+
+        Frames {
+            town_spr,
+            renderer,
+            perspective: None,
+        }
+    }
+}
+
+impl Observer for Frames {
+    fn on_round(&mut self, round: usize, _team: Team, game: &Game) {
+        let view = self.perspective.map(|team| game.view(team));
+        let visible = view.as_ref().map(|view| view.visible());
+        match &view {
+            Some(view) => self.renderer.render(view.players(), visible),
+            None => self.renderer.render(game.get_players(), None),
+        }
+
+        if let Some(mut image) = RgbaImage::from_raw(1000, 1000, self.renderer.im_buff.clone()) {
+            for i in (0..self.renderer.towns.len()).filter(|&i| visible.is_none_or(|v| v[i])) {
+                let t = self.renderer.towns.get_town(i);
+                let pt = self.renderer.cache.get_pixel(t.x, t.y);
+                overlay(&mut image, &self.town_spr, (pt.x-25) as i64, (pt.y-25) as i64);
+            }
+            let _res = image.save(format!("src/assets/{round}.png"));
+        }
+    }
+}
@@ -8,10 +8,22 @@ use glm::{mat4, vec3};
 use glm::ext::{rotate, scale};
 use std::f32::consts::{FRAC_PI_4, PI};
 use geo::{LineString, Polygon, ConvexHull};
+use super::game::{Board, Player, Team};
 
 type Color = [u8; 4];
-const BLUE: Color = [0, 0, 255, 255];
-const RED: Color = [255, 0, 0, 255];
+
+const FOG: Color = [90, 90, 90, 255];
+
+fn team_color(team: Team) -> Color {
+    match team {
+        Team::Blue => [0, 0, 255, 255],
+        Team::Red => [255, 0, 0, 255],
+        Team::Green => [0, 200, 0, 255],
+        Team::Purple => [160, 0, 255, 255],
+        Team::Orange => [255, 128, 0, 255],
+        Team::Cyan => [0, 220, 220, 255],
+    }
+}
 
 pub struct Renderer {
     pub cache: Cache, // TEMPORALLY PUBLIC
@@ -86,9 +98,9 @@ impl Renderer {
         }
     }
 
-    pub fn init() -> Renderer {
+    pub fn init(seed: u64, board: &Board) -> Renderer {
         // create perlin noise
-        let perlin = PerlinNoise2D::new(8, 2.5, 0.5, 1.0, 2.05, (100.0, 100.0), 2.0, 101);
+        let perlin = PerlinNoise2D::new(8, 2.5, 0.5, 1.0, 2.05, (100.0, 100.0), 2.0, seed as i32);
 
         // setup transformation matrix
         let m = mat4(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0);
@@ -102,12 +114,13 @@ impl Renderer {
         // return renderer object with empty buffer
         Renderer {
             cache: cache,
-            towns: TownsR::gen(),
+            towns: TownsR::gen(board),
             im_buff: vec![0; 1000*1000*4],
         }
     }
 
-    pub fn render(&mut self, b_towns: Vec<usize>, b_cap: usize, r_towns: Vec<usize>, r_cap: usize) {
+    /// Draws the map. Towns not `visible`, if given, are drawn under fog.
+    pub fn render(&mut self, players: &[Player], visible: Option<&[bool]>) {
         // draw pixels on canvas
         for x in 0..1000 {
             for y in 0..1000 {
@@ -118,8 +131,19 @@ impl Renderer {
             }
         }
 
+        // draw fog
+        if let Some(visible) = visible {
+            for i in (0..self.towns.len()).filter(|&i| !visible[i]) {
+                let t = self.towns.get_town(i);
+                self.draw_circle(t.x - 40, t.y - 40, 80, FOG);
+            }
+        }
+
         // draw towns
-        self.draw_town(b_towns, b_cap, BLUE);
-        self.draw_town(r_towns, r_cap, RED);
+        for player in players {
+            if player.is_alive() {
+                self.draw_town(player.get_towns_id(), player.get_capital_id(), team_color(player.team));
+            }
+        }
     }
 }
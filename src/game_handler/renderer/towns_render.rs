@@ -1,6 +1,4 @@
-extern crate rand;
-
-use rand::{Rng, thread_rng};
+use super::super::game::Board;
 
 #[derive(Copy, Clone)]
 pub struct TownR {
@@ -14,25 +12,19 @@ pub struct TownsR {
 }
 
 impl TownsR {
-   pub fn gen() -> TownsR {
+   /// Places the board's towns on the canvas.
+   pub fn gen(board: &Board) -> TownsR {
         let mut towns = TownsR {
             arr: vec![],
             _b_frontier: vec![]
         };
 
-        let mut rng = thread_rng();
-        for i in 0..(4*4) {
-            let x: f32 = rng.gen();
-            let y: f32 = rng.gen();
-            let x = (x*150.0) as i32;
-            let y = (y*150.0) as i32;
-
-            let x0 = (i%4)*250;
-            let y0 = (i/4)*250;
+        for i in 0..board.len() {
+            let (x, y) = board.get_position(i);
 
             towns.arr.push(TownR {
-                x: x0 + x + 50,
-                y: y0 + y + 50,
+                x: (x*1000.0) as i32,
+                y: (y*1000.0) as i32,
             });
         }
 
@@ -42,4 +34,8 @@ impl TownsR {
     pub fn get_town(&self, i: usize) -> TownR {
         self.arr[i]
     }
+
+    pub fn len(&self) -> usize {
+        self.arr.len()
+    }
 }
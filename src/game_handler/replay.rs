@@ -0,0 +1,70 @@
+//! Replay files: every action taken during a game, dice included, so the
+//! game can be rebuilt exactly.
+
+use std::fs;
+use serde::{Serialize, Deserialize};
+use super::game::{Game, Action, Team, SaveError};
+
+/// One action, as it was played.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub team: Team,
+    pub action: Action,
+    /// Capture dice roll, only for `Action::Capture`.
+    pub roll: Option<f32>,
+    /// The action was illegal and the team forfeited instead.
+    #[serde(default)]
+    pub forfeit: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// The game before the first record, as a save file has it: recording
+    /// a loaded game starts where it was left off.
+    pub start: Game,
+    pub records: Vec<Record>,
+}
+
+impl Replay {
+    /// Empty replay starting from `game` as it is now.
+    pub fn new(game: &Game) -> Replay {
+        Replay {
+            start: game.clone(),
+            records: vec![],
+        }
+    }
+
+    pub fn push(&mut self, team: Team, action: Action, roll: Option<f32>) {
+        self.records.push(Record {
+            team,
+            action,
+            roll,
+            forfeit: false,
+        });
+    }
+
+    /// `action` was illegal, `team` forfeited.
+    pub fn push_forfeit(&mut self, team: Team, action: Action) {
+        self.records.push(Record {
+            team,
+            action,
+            roll: None,
+            forfeit: true,
+        });
+    }
+
+    /// The game as it was before the first record.
+    pub fn start(&self) -> Game {
+        self.start.clone()
+    }
+
+    pub fn load(path: &str) -> Result<Replay, SaveError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SaveError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+//! Outcome of a game, for callers and tournament tooling to consume.
+
+use serde::{Serialize, Deserialize};
+use super::game::{Game, Ending, Team};
+
+/// Resources of one team over the game, one entry per round.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Series {
+    pub team: Team,
+    pub gold: Vec<i32>,
+    pub influence: Vec<i32>,
+    pub towns: Vec<usize>,
+}
+
+/// Gold, influence, available specialty points and points per perk, as
+/// `Player::get_stats` gives them.
+pub type Stats = (i32, i32, i32, [i32; 3]);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameResult {
+    /// Winning alliance, empty on a draw.
+    pub winners: Vec<Team>,
+    pub ending: Ending,
+    pub rounds: usize,
+    /// Final `Player::get_stats` of every team, in turn order.
+    pub stats: Vec<(Team, Stats)>,
+    /// Resource time series of every team, in turn order.
+    pub series: Vec<Series>,
+}
+
+impl Series {
+    /// Empty time series for every team of `game`.
+    pub fn for_game(game: &Game) -> Vec<Series> {
+        game.get_players().iter().map(|p| Series {
+            team: p.team,
+            gold: vec![],
+            influence: vec![],
+            towns: vec![],
+        }).collect()
+    }
+
+    /// Appends the current resources of every team to their series.
+    pub fn record(series: &mut [Series], game: &Game) {
+        for (s, player) in series.iter_mut().zip(game.get_players()) {
+            s.gold.push(player.gold);
+            s.influence.push(player.influence);
+            s.towns.push(player.get_towns_id().len());
+        }
+    }
+}
@@ -1,70 +1,207 @@
 mod game_handler;
+mod ai;
+mod tournament;
+mod batch;
 
-use game_handler::GameHandler;
-use game_handler::game::{Player, Town, Action};
+use std::time::Duration;
+use ai::{ia, greedy, Random, Endgame, Deepening, Limit};
+use ai::eval::{Material, Threat};
+use ai::expectimax::Expectimax;
+use ai::mcts::{Mcts, Budget};
+use game_handler::{GameHandler, IllegalActionPolicy};
+use game_handler::replay::Replay;
+use game_handler::observer::Frames;
+use tournament::Tournament;
+use game_handler::game::{Game, GameConfig, Topology, Team, Victory, Perk};
 
-type Score = i32;
-
-const AN_AWFUL_LOT: i32 = 99999999;
-const TURNS_AHEAD: i32 = 4;
-
-fn max<T: Ord>(a: T, b: T) -> T {
-    if a > b {a} else {b}
+fn parse_team(name: &str) -> Team {
+    match Team::ALL.iter().find(|team| team.to_string().eq_ignore_ascii_case(name)) {
+        Some(&team) => team,
+        None => panic!("unknown team {}", name),
+    }
 }
 
-fn max_out_of_list<T: Ord + Copy>(array: &Vec<T>) -> T {
-    let mut greatest_value = array[0];
-    for value in array {
-        greatest_value = max(greatest_value, *value);
+fn parse_victory(victory: &str) -> Victory {
+    match victory.split_once(':') {
+        Some(("towns", n)) => Victory::Towns(n.parse().expect("towns victory must look like towns:10")),
+        Some(("influence", x)) => Victory::Influence(x.parse().expect("influence victory must look like influence:5000")),
+        Some(("perk", "flagellation")) => Victory::Perk(Perk::Flagellation),
+        Some(("perk", "communion")) => Victory::Perk(Perk::Communion),
+        Some(("perk", "display")) => Victory::Perk(Perk::Display),
+        _ => panic!("unknown victory {}", victory),
     }
-
-    greatest_value
 }
 
-/// Pure function, static. Gets a list of actions, returns most profitable action.
-fn ia(mut actions: Vec<Action>, towns: &Vec<Town>, player: &Player, rival: &Player) -> Action {
-    let sorting_key = |action: &Action| {
-        if let Action::Capture(_town) = action {
-            let c = |g: f32, i: f32| -> f32 {
-                let g = g * 11.0;
-                let i = (i+0.001) * 250.0;
-    
-                1.0 - ((g/i) + 1.0).powf(-1.0)
-            };
-            let chance = c(player.gold as f32, rival.influence as f32);
-            if chance >= 0.5 { AN_AWFUL_LOT } else { 0 }
-        } else {
-            get_score(action, towns, player, rival, TURNS_AHEAD)
+fn main() {
+    // Options:
+    //   --seed N                 replay a game, a random seed is picked otherwise
+    //   --size WxH               board size, e.g. 6x6
+    //   --topology T             grid, grid8, hex or planar
+    //   --players N              number of teams, 2 to 6
+    //   --capitals A,B,...       starting towns in turn order
+    //   --alliances A+B,C+D      teams winning together, e.g. blue+green,red+purple
+    //   --gold-transfer on       let allies send each other gold
+    //   --fog on                 fog of war, teams only see near their towns
+    //   --perspective TEAM       draw the frames as seen by TEAM
+    //   --max-rounds N           decide the game on points after N turns
+    //   --max-actions N          make a team pass after N actions in a turn
+    //   --victory V,...          extra ways to win: towns:N, influence:X or
+    //                            perk:flagellation|communion|display
+    //   --map FILE               play on a map file instead of a generated board
+    //   --save-map FILE          write the board out as a map file
+    //   --load FILE              resume a saved game
+    //   --checkpoint FILE        save the game state after every round
+    //   --record FILE            write a replay of the game
+    //   --replay FILE            play a recorded game back
+    //   --illegal P              forfeit, pass or panic on illegal actions
+    //   --tournament N           pit every IA against each other on N seeds
+    //   --batch N                play N games over worker threads, print stats
+    //   --threads N              worker threads for batches
+    //   --render on|off          write a frame every round, on by default
+    //                            but off in tournaments
+    let mut config = GameConfig::new(rand::random());
+    let mut map: Option<String> = None;
+    let mut save_map: Option<String> = None;
+    let mut load: Option<String> = None;
+    let mut checkpoint: Option<String> = None;
+    let mut record: Option<String> = None;
+    let mut replay: Option<String> = None;
+    let mut policy = IllegalActionPolicy::default();
+    let mut tournament: Option<usize> = None;
+    let mut render: Option<bool> = None;
+    let mut batch: Option<usize> = None;
+    let mut perspective: Option<Team> = None;
+    let mut threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().unwrap_or_else(|| panic!("{} expects a value", flag));
+        match flag.as_str() {
+            "--seed" => config.seed = value.parse().expect("seed must be an unsigned integer"),
+            "--size" => {
+                let size: Vec<usize> = value.split('x')
+                    .map(|n| n.parse().expect("board size must look like 6x6"))
+                    .collect();
+                assert!(size.len() == 2, "board size must look like 6x6");
+                config.width = size[0];
+                config.height = size[1];
+            },
+            "--topology" => config.topology = match value.as_str() {
+                "grid" => Topology::Grid,
+                "grid8" => Topology::Grid8,
+                "hex" => Topology::Hex,
+                "planar" => Topology::Planar,
+                _ => panic!("unknown topology {}", value),
+            },
+            "--players" => {
+                let n: usize = value.parse().expect("players must be a number");
+                assert!((2..=Team::ALL.len()).contains(&n), "2 to {} teams can play", Team::ALL.len());
+                config.teams = Team::ALL[..n].to_vec();
+            },
+            "--capitals" => config.capitals = value.split(',')
+                .map(|id| id.parse().expect("capitals must be town ids"))
+                .collect(),
+            "--alliances" => config.alliances = value.split(',')
+                .map(|alliance| alliance.split('+').map(parse_team).collect())
+                .collect(),
+            "--gold-transfer" => config.gold_transfer = value == "on",
+            "--fog" => config.fog = value == "on",
+            "--perspective" => perspective = Some(parse_team(&value)),
+            "--max-rounds" => config.max_rounds = Some(value.parse().expect("max rounds must be a number")),
+            "--max-actions" => config.max_actions = Some(value.parse().expect("max actions must be a number")),
+            "--victory" => config.victory = value.split(',').map(parse_victory).collect(),
+            "--map" => map = Some(value),
+            "--save-map" => save_map = Some(value),
+            "--load" => load = Some(value),
+            "--checkpoint" => checkpoint = Some(value),
+            "--record" => record = Some(value),
+            "--replay" => replay = Some(value),
+            "--tournament" => tournament = Some(value.parse().expect("tournament must be a number of seeds")),
+            "--batch" => batch = Some(value.parse().expect("batch must be a number of games")),
+            "--threads" => threads = value.parse().expect("threads must be a number"),
+            "--render" => render = Some(value == "on"),
+            "--illegal" => policy = match value.as_str() {
+                "forfeit" => IllegalActionPolicy::Forfeit,
+                "pass" => IllegalActionPolicy::Pass,
+                "panic" => IllegalActionPolicy::Panic,
+                _ => panic!("unknown policy {}", value),
+            },
+            _ => panic!("unknown option {}", flag),
         }
-    };
+    }
 
-    // Pick the action with the highest score
-    actions.sort_by_cached_key(sorting_key);
-    actions[actions.len()-1]
-}
+    if let Some(games) = tournament {
+        let mut tournament = Tournament::new(config, games);
+        tournament.register("lookahead", || Box::new(ia));
+        tournament.register("endgame", || Box::new(Endgame));
+        tournament.register("deepening", || Box::new(Deepening::new(Limit::Time(Duration::from_millis(50)))));
+        tournament.register("deepening-100k", || Box::new(Deepening::new(Limit::Nodes(100_000))));
+        tournament.register("deepening-threat", || Box::new(Deepening::with_evaluator(Limit::Time(Duration::from_millis(50)), Threat)));
+        tournament.register("expectimax", || Box::new(Expectimax::new(2)));
+        tournament.register("expectimax-material", || Box::new(Expectimax::with_evaluator(2, Material)));
+        tournament.register("expectimax-threat", || Box::new(Expectimax::with_evaluator(2, Threat)));
+        tournament.register("mcts", || Box::new(Mcts::new(Budget::Iterations(1000))));
+        tournament.register("mcts-threat", || Box::new(Mcts::with_evaluator(Budget::Iterations(1000), Threat)));
+        tournament.register("mcts-100ms", || Box::new(Mcts::new(Budget::Time(Duration::from_millis(100)))));
+        tournament.register("greedy", || Box::new(greedy));
+        tournament.register("random", || Box::new(Random::new()));
+        tournament.set_rendering(render.unwrap_or(false));
+        print!("{}", tournament.run());
+        return;
+    }
+
+    if let Some(games) = batch {
+        print!("{}", batch::run(|| Box::new(ia), &config, games, threads));
+        return;
+    }
 
-fn get_score(action: &Action, _towns: &Vec<Town>, player: &Player, _rival: &Player, turns: i32) -> Score {
-    let mut player2 = player.clone();
-    player2.do_action2(*action);
-    player2.end_turn();
-    if turns == 0 {
-        get_player_score(&player2)
-    } else if let Action::Capture(_) = action {
-        0
+    if let Some(path) = replay {
+        let replay = match Replay::load(&path) {
+            Ok(replay) => replay,
+            Err(e) => panic!("{}", e),
+        };
+        GameHandler::with_game(&ia, replay.start()).replay(&replay);
+        return;
+    }
+
+    println!("Initializing game instance [1/2]");
+    let game = if let Some(path) = load {
+        match Game::load(&path) {
+            Ok(game) => game,
+            Err(e) => panic!("{}", e),
+        }
+    } else if let Some(path) = map {
+        match Game::from_map(config, &path) {
+            Ok(game) => game,
+            Err(e) => panic!("{}", e),
+        }
     } else {
-        let mut scores = vec![];
-        for action in player2.get_available_actions(_towns, _rival) {
-            scores.push(get_score(&action, _towns, &player2, _rival, turns-1));
+        Game::init(config)
+    };
+    let mut gh = match (render.unwrap_or(true), perspective) {
+        (true, Some(team)) => {
+            let frames = Frames::with_perspective(&game, team);
+            let mut gh = GameHandler::headless(&ia, game);
+            gh.add_observer(Box::new(frames));
+            gh
+        },
+        (true, None) => GameHandler::with_game(&ia, game),
+        (false, _) => GameHandler::headless(&ia, game),
+    };
+
+    if let Some(path) = save_map {
+        if let Err(e) = gh.get_game().save_map(&path) {
+            panic!("{}", e);
         }
-        max_out_of_list(&scores)
     }
-}
 
-fn get_player_score(player: &Player) -> i32 {
-    ((player.gold as f32 / 150.0 + player.influence as f32 * 3.0 / 11.0) * 1000.0) as i32
-}
+    gh.set_policy(policy);
+    if let Some(path) = checkpoint {
+        gh.set_checkpoint(&path);
+    }
+    if let Some(path) = record {
+        gh.set_record(&path);
+    }
 
-fn main() {
-    let mut gh = GameHandler::new(&ia);
     gh.simulate();
 }
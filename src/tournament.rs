@@ -0,0 +1,186 @@
+//! Round-robin tournaments between IA's.
+
+use std::fmt;
+use crate::game_handler::GameHandler;
+use crate::game_handler::agent::AgentFactory;
+use crate::game_handler::observer::Frames;
+use crate::game_handler::game::{Game, GameConfig};
+
+const ELO_START: f64 = 1500.0;
+const ELO_K: f64 = 32.0;
+/// Turn limit for configs without one, so two passive IA's can't stall a
+/// tournament or a batch.
+pub const MAX_ROUNDS: usize = 400;
+/// Action limit per turn for configs without one, so an IA can't stall a
+/// single turn either.
+pub const MAX_ACTIONS: usize = 100;
+
+pub struct Tournament {
+    entrants: Vec<(String, AgentFactory)>,
+    config: GameConfig,
+    games: usize,
+    rendering: bool,
+}
+
+/// How an IA did against another.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Record {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+pub struct Standings {
+    pub names: Vec<String>,
+    /// `records[a][b]` is how `a` did against `b`.
+    pub records: Vec<Vec<Record>>,
+    pub elo: Vec<f64>,
+}
+
+impl Tournament {
+    /// Every pair of IA's plays `games` seeds, counting up from the config
+    /// seed, once on each side. Only the first two teams of the config play,
+    /// and games print nothing.
+    pub fn new(config: GameConfig, games: usize) -> Tournament {
+        assert!(config.teams.len() >= 2, "a game needs at least two teams");
+
+        Tournament {
+            entrants: vec![],
+            config,
+            games,
+            rendering: false,
+        }
+    }
+
+    /// Enters an IA. `agent` builds a fresh one for every game.
+    pub fn register(&mut self, name: &str, agent: AgentFactory) {
+        self.entrants.push((name.to_string(), agent));
+    }
+
+    /// Writes PNG frames for every game, off by default.
+    pub fn set_rendering(&mut self, rendering: bool) {
+        self.rendering = rendering;
+    }
+
+    pub fn run(&self) -> Standings {
+        let n = self.entrants.len();
+        let mut standings = Standings {
+            names: self.entrants.iter().map(|(name, _)| name.clone()).collect(),
+            records: vec![vec![Record::default(); n]; n],
+            elo: vec![ELO_START; n],
+        };
+
+        for a in 0..n {
+            for b in (a + 1)..n {
+                for k in 0..self.games {
+                    let seed = self.config.seed.wrapping_add(k as u64);
+                    for (first, second) in [(a, b), (b, a)] {
+                        let score = self.play(first, second, seed);
+                        standings.add(first, second, score);
+                    }
+                }
+            }
+        }
+
+        standings
+    }
+
+    /// Plays a game, `first` moving first. Returns `first`'s score.
+    fn play(&self, first: usize, second: usize, seed: u64) -> f64 {
+        let mut config = self.config.clone();
+        config.seed = seed;
+        config.teams.truncate(2);
+        config.alliances.clear();
+        config.max_rounds = config.max_rounds.or(Some(MAX_ROUNDS));
+        config.max_actions = config.max_actions.or(Some(MAX_ACTIONS));
+        let team = config.teams[0];
+
+        let agents = vec![(self.entrants[first].1)(), (self.entrants[second].1)()];
+        let mut gh = GameHandler::with_agents(agents, Game::init(config));
+        gh.set_verbose(false);
+        if self.rendering {
+            let frames = Frames::new(gh.get_game());
+            gh.add_observer(Box::new(frames));
+        }
+        let winners = gh.simulate().winners;
+
+        if winners.contains(&team) {
+            1.0
+        } else if winners.is_empty() {
+            0.5
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Standings {
+    /// Counts a game between `a` and `b`, `score` being `a`'s, and updates
+    /// their ratings.
+    fn add(&mut self, a: usize, b: usize, score: f64) {
+        let (rec_a, rec_b) = if score == 1.0 {
+            (Record { wins: 1, ..Default::default() }, Record { losses: 1, ..Default::default() })
+        } else if score == 0.0 {
+            (Record { losses: 1, ..Default::default() }, Record { wins: 1, ..Default::default() })
+        } else {
+            (Record { draws: 1, ..Default::default() }, Record { draws: 1, ..Default::default() })
+        };
+        self.records[a][b].add(rec_a);
+        self.records[b][a].add(rec_b);
+
+        let expected = 1.0 / (1.0 + 10f64.powf((self.elo[b] - self.elo[a]) / 400.0));
+        let delta = ELO_K * (score - expected);
+        self.elo[a] += delta;
+        self.elo[b] -= delta;
+    }
+
+    /// Overall record of IA `a`.
+    pub fn total(&self, a: usize) -> Record {
+        let mut total = Record::default();
+        for record in &self.records[a] {
+            total.add(*record);
+        }
+
+        total
+    }
+}
+
+impl Record {
+    fn add(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.wins, self.draws, self.losses)
+    }
+}
+
+/// Win/draw/loss table, best rated IA first.
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| self.elo[b].total_cmp(&self.elo[a]));
+        let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(6);
+
+        write!(f, "{:width$}", "W/D/L")?;
+        for &b in &order {
+            write!(f, " {:>width$}", self.names[b])?;
+        }
+        writeln!(f, " {:>width$} {:>6}", "total", "Elo")?;
+
+        for &a in &order {
+            write!(f, "{:width$}", self.names[a])?;
+            for &b in &order {
+                let cell = if a == b { "-".to_string() } else { self.records[a][b].to_string() };
+                write!(f, " {:>width$}", cell)?;
+            }
+            writeln!(f, " {:>width$} {:>6.0}", self.total(a).to_string(), self.elo[a])?;
+        }
+
+        Ok(())
+    }
+}